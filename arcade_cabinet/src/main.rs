@@ -4,7 +4,7 @@ use intcode::*;
 use util::*;
 
 use std::{
-  collections::{VecDeque, HashMap},
+  collections::HashMap,
   io,
   thread::{sleep},
   time::{Duration, Instant}
@@ -119,7 +119,7 @@ pub fn main() -> Result<(), failure::Error> {
   println!("Starting arcade cabinet");
 
   let input = include_str!("../../input/2019/day13.txt");
-  let mut program = &intcode_parser(input);
+  let mut program = &intcode_parser(input).unwrap_or_else(|error| panic!("{}", error));
   let mut machine = Machine::new(program);
 
   // Terminal initialization
@@ -134,7 +134,7 @@ pub fn main() -> Result<(), failure::Error> {
     // Setup event handlers
     let events = Events::new();
 
-    let mut output_buffer = VecDeque::new();
+    let mut frames = Framer::new(Schema::named(&["x", "y", "value"]));
     let mut screen = HashMap::new();
 
     let mut automatic = false;
@@ -192,12 +192,8 @@ pub fn main() -> Result<(), failure::Error> {
         },
         Some(Action::Halt) => break 'main,
         Some(Action::Output(value)) => {
-          output_buffer.push_back(value);
-          
-          if output_buffer.len() >= 3 {
-            let x = output_buffer.pop_front().unwrap();
-            let y = output_buffer.pop_front().unwrap();
-            let value = output_buffer.pop_front().unwrap();
+          if let Some(record) = frames.push(value) {
+            let (x, y, value) = (record["x"], record["y"], record["value"]);
 
             if x == -1 && y == 0 {
               score = value;
@@ -223,7 +219,7 @@ pub fn main() -> Result<(), failure::Error> {
             repaint_screen(&mut terminal, &screen, score);
           }
         },
-        None => {}
+        Some(Action::BudgetExhausted) | None => {}
       }
     }
 
@@ -0,0 +1,72 @@
+use std::time::{Duration, Instant};
+
+use intcode::*;
+
+const ITERATIONS: u32 = 10;
+
+// A machine ready to be cloned for each run, as the solutions that start
+// many machines from one program do.
+fn prepare(program: &IntcodeProgram, cache: bool) -> Machine {
+    let mut machine = Machine::new(program);
+    machine.set_instruction_cache(cache);
+    machine.warm_instruction_cache();
+    machine
+}
+
+fn run_to_halt(template: &Machine, inputs: &[isize]) -> Vec<isize> {
+    let mut machine = template.clone();
+
+    for input in inputs {
+        machine.push_input(*input);
+    }
+
+    let mut outputs = Vec::new();
+    loop {
+        match machine.run() {
+            Action::Output(value) => outputs.push(value),
+            Action::Halt => return outputs,
+            Action::RequiresInput => panic!("Ran out of input"),
+            Action::BudgetExhausted => unreachable!()
+        }
+    }
+}
+
+fn sensor_boost(program: &IntcodeProgram, cache: bool) {
+    run_to_halt(&prepare(program, cache), &[2]);
+}
+
+fn tractor_beam(program: &IntcodeProgram, cache: bool) {
+    let drone = prepare(program, cache);
+
+    for y in 0..50 {
+        for x in 0..50 {
+            run_to_halt(&drone, &[x, y]);
+        }
+    }
+}
+
+fn measure<F: Fn()>(f: F) -> Duration {
+    (0..ITERATIONS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn bench(name: &str, program: &IntcodeProgram, f: fn(&IntcodeProgram, bool)) {
+    let uncached = measure(|| f(program, false));
+    let cached = measure(|| f(program, true));
+
+    println!(
+        "{:<16} uncached {:>10.3?}  cached {:>10.3?}  speedup {:.2}x",
+        name, uncached, cached, uncached.as_secs_f64() / cached.as_secs_f64()
+    );
+}
+
+fn main() {
+    bench("day 9 part 2", &intcode_parser(include_str!("../../input/2019/day9.txt")).unwrap(), sensor_boost);
+    bench("day 19 part 1", &intcode_parser(include_str!("../../input/2019/day19.txt")).unwrap(), tractor_beam);
+}
@@ -0,0 +1,166 @@
+use std::fmt;
+use std::mem;
+
+use crate::{Action, DenseMemory, Machine, Memory, Word};
+
+// A line of output from an ASCII program, or a value outside the ASCII
+// range such as the answer a program reports once it is done talking.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AsciiOutput<W = isize> {
+    Text(String),
+    Value(W)
+}
+
+impl<W: Word> fmt::Display for AsciiOutput<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsciiOutput::Text(text) => write!(f, "{}", text),
+            AsciiOutput::Value(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+// Wraps a machine that reads and writes text one character per value.
+pub struct AsciiMachine<W: Word = isize, M: Memory<W> = DenseMemory<W>> {
+    machine: Machine<W, M>,
+    text: String,
+    value: Option<W>,
+    halted: bool
+}
+
+impl<W: Word, M: Memory<W>> AsciiMachine<W, M> {
+    pub fn new(machine: Machine<W, M>) -> Self {
+        AsciiMachine { machine, text: String::new(), value: None, halted: false }
+    }
+
+    pub fn machine(&self) -> &Machine<W, M> {
+        &self.machine
+    }
+
+    pub fn into_machine(self) -> Machine<W, M> {
+        self.machine
+    }
+
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    // Queues `line` as input, followed by a newline.
+    pub fn send_line(&mut self, line: &str) {
+        for byte in line.bytes().chain(Some(b'\n')) {
+            self.machine.push_input(W::from_isize(byte as isize));
+        }
+    }
+
+    // Runs until the program finishes a line of text or outputs a non-ASCII
+    // value. A partial line is returned once the program halts, waits for
+    // input or runs out of budget; after that this returns None until it can
+    // make progress again.
+    pub fn read_line(&mut self) -> Option<AsciiOutput<W>> {
+        if let Some(value) = self.value.take() {
+            return Some(AsciiOutput::Value(value));
+        }
+
+        while !self.halted {
+            let value = match self.machine.run() {
+                Action::Output(value) => value,
+                Action::RequiresInput | Action::BudgetExhausted => break,
+                Action::Halt => {
+                    self.halted = true;
+                    break;
+                }
+            };
+
+            match value.to_isize().filter(|value| (0..128).contains(value)) {
+                Some(10) => return Some(AsciiOutput::Text(mem::take(&mut self.text))),
+                Some(c) => self.text.push(c as u8 as char),
+
+                None if self.text.is_empty() => return Some(AsciiOutput::Value(value)),
+                None => {
+                    self.value = Some(value);
+                    return Some(AsciiOutput::Text(mem::take(&mut self.text)));
+                }
+            }
+        }
+
+        if self.text.is_empty() {
+            None
+        } else {
+            Some(AsciiOutput::Text(mem::take(&mut self.text)))
+        }
+    }
+
+    // Reads lines up to and including `prompt`, or everything the program
+    // outputs before it stops if the prompt never appears.
+    pub fn read_until_prompt(&mut self, prompt: &str) -> Vec<AsciiOutput<W>> {
+        let mut lines = Vec::new();
+
+        while let Some(line) = self.read_line() {
+            let done = matches!(&line, AsciiOutput::Text(text) if text == prompt);
+            lines.push(line);
+
+            if done {
+                break;
+            }
+        }
+
+        lines
+    }
+
+    // Reads everything the program outputs before it halts or waits for input.
+    pub fn read_to_end(&mut self) -> Vec<AsciiOutput<W>> {
+        let mut lines = Vec::new();
+
+        while let Some(line) = self.read_line() {
+            lines.push(line);
+        }
+
+        lines
+    }
+}
+
+impl<W: Word, M: Memory<W>> Machine<W, M> {
+    pub fn into_ascii(self) -> AsciiMachine<W, M> {
+        AsciiMachine::new(self)
+    }
+}
+
+#[test]
+fn test_read_line() {
+    let program = crate::assemble("
+        OUT #72
+        OUT #105
+        OUT #1000
+        OUT #10
+        OUT #63
+        IN -> [0]
+        HALT
+    ").unwrap();
+
+    let mut machine = Machine::new(&program).into_ascii();
+    assert_eq!(machine.read_line(), Some(AsciiOutput::Text("Hi".to_string())));
+    assert_eq!(machine.read_line(), Some(AsciiOutput::Value(1000)));
+    assert_eq!(machine.read_line(), Some(AsciiOutput::Text("".to_string())));
+    assert_eq!(machine.read_line(), Some(AsciiOutput::Text("?".to_string())));
+    assert_eq!(machine.read_line(), None);
+    assert!(!machine.halted());
+
+    machine.send_line("");
+    assert_eq!(machine.read_line(), None);
+    assert!(machine.halted());
+}
+
+#[test]
+fn test_springdroid() {
+    let program: crate::IntcodeProgram = crate::intcode_parser(include_str!("../../input/2019/day21.txt")).unwrap();
+    let mut machine = Machine::new(&program).into_ascii();
+
+    let prompt = machine.read_until_prompt("Input instructions:");
+    assert_eq!(prompt.last(), Some(&AsciiOutput::Text("Input instructions:".to_string())));
+
+    for line in ["NOT A J", "NOT B T", "OR T J", "NOT C T", "OR T J", "AND D J", "WALK"].iter() {
+        machine.send_line(line);
+    }
+
+    assert_eq!(machine.read_to_end().last(), Some(&AsciiOutput::Value(19357544)));
+}
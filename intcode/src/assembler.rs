@@ -0,0 +1,357 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::{DenseMemory, Instruction, IntcodeProgram, Memory};
+
+const OPCODES: [isize; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 99];
+
+// The opcode and operand count for a mnemonic, read off the instruction
+// each opcode decodes to so the two cannot disagree.
+fn lookup(mnemonic: &str) -> Option<(isize, usize)> {
+    OPCODES.iter().find_map(|&opcode| {
+        let memory: DenseMemory = DenseMemory::from_program(&vec![opcode, 0, 0, 0]);
+        let instruction = Instruction::decode(&memory, 0).ok()?;
+        (instruction.mnemonic() == mnemonic).then(|| (opcode, instruction.size() - 1))
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AssemblerError {
+    UnknownMnemonic { line: usize, mnemonic: String },
+    UnknownDirective { line: usize, directive: String },
+    WrongOperandCount { line: usize, mnemonic: String, expected: usize, found: usize },
+    InvalidOperand { line: usize, operand: String },
+    InvalidLabel { line: usize, label: String },
+    DuplicateLabel { line: usize, label: String },
+    UndefinedLabel { line: usize, label: String },
+    Overflow { line: usize },
+}
+
+impl AssemblerError {
+    pub fn line(&self) -> usize {
+        match self {
+            AssemblerError::UnknownMnemonic { line, .. } => *line,
+            AssemblerError::UnknownDirective { line, .. } => *line,
+            AssemblerError::WrongOperandCount { line, .. } => *line,
+            AssemblerError::InvalidOperand { line, .. } => *line,
+            AssemblerError::InvalidLabel { line, .. } => *line,
+            AssemblerError::DuplicateLabel { line, .. } => *line,
+            AssemblerError::UndefinedLabel { line, .. } => *line,
+            AssemblerError::Overflow { line } => *line,
+        }
+    }
+}
+
+impl fmt::Display for AssemblerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssemblerError::UnknownMnemonic { line, mnemonic } =>
+                write!(f, "line {}: unknown mnemonic '{}'", line, mnemonic),
+
+            AssemblerError::UnknownDirective { line, directive } =>
+                write!(f, "line {}: unknown directive '{}'", line, directive),
+
+            AssemblerError::WrongOperandCount { line, mnemonic, expected, found } =>
+                write!(f, "line {}: {} takes {} operands but {} were given", line, mnemonic, expected, found),
+
+            AssemblerError::InvalidOperand { line, operand } =>
+                write!(f, "line {}: invalid operand '{}'", line, operand),
+
+            AssemblerError::InvalidLabel { line, label } =>
+                write!(f, "line {}: invalid label '{}'", line, label),
+
+            AssemblerError::DuplicateLabel { line, label } =>
+                write!(f, "line {}: label '{}' is already defined", line, label),
+
+            AssemblerError::UndefinedLabel { line, label } =>
+                write!(f, "line {}: undefined label '{}'", line, label),
+
+            AssemblerError::Overflow { line } =>
+                write!(f, "line {}: value does not fit in a word", line),
+        }
+    }
+}
+
+impl std::error::Error for AssemblerError {}
+
+#[derive(Debug)]
+enum Term {
+    Number(isize),
+    Label(String)
+}
+
+// A sum of numbers and labels, e.g. `table+3` or `-2`.
+#[derive(Debug)]
+struct Expression(Vec<(isize, Term)>);
+
+impl Expression {
+    fn parse(line: usize, text: &str) -> Result<Expression, AssemblerError> {
+        let invalid = || AssemblerError::InvalidOperand { line, operand: text.to_string() };
+
+        let mut terms = Vec::new();
+        let mut sign = 1;
+        let mut rest = text.trim();
+
+        if rest.is_empty() {
+            return Err(invalid());
+        }
+
+        loop {
+            if let Some(stripped) = rest.strip_prefix('-') {
+                sign = -sign;
+                rest = stripped.trim_start();
+                continue;
+            }
+
+            if let Some(stripped) = rest.strip_prefix('+') {
+                rest = stripped.trim_start();
+                continue;
+            }
+
+            let end = rest.find(['+', '-']).unwrap_or(rest.len());
+            let token = rest[..end].trim();
+
+            let term = if token.starts_with(|c: char| c.is_ascii_digit()) {
+                Term::Number(token.parse().map_err(|_| invalid())?)
+            } else if is_identifier(token) {
+                Term::Label(token.to_string())
+            } else {
+                return Err(invalid());
+            };
+
+            terms.push((sign, term));
+            sign = 1;
+            rest = rest[end..].trim_start();
+
+            if rest.is_empty() {
+                return Ok(Expression(terms));
+            }
+        }
+    }
+
+    fn evaluate(&self, line: usize, labels: &BTreeMap<String, usize>) -> Result<isize, AssemblerError> {
+        let mut total: isize = 0;
+
+        for (sign, term) in &self.0 {
+            let value = match term {
+                Term::Number(value) => *value,
+                Term::Label(label) => *labels.get(label)
+                    .ok_or_else(|| AssemblerError::UndefinedLabel { line, label: label.clone() })? as isize
+            };
+
+            total = value.checked_mul(*sign)
+                .and_then(|value| total.checked_add(value))
+                .ok_or(AssemblerError::Overflow { line })?;
+        }
+
+        Ok(total)
+    }
+}
+
+#[derive(Debug)]
+enum Statement {
+    Instruction(isize, Vec<(isize, Expression)>),
+    Data(Vec<Expression>)
+}
+
+impl Statement {
+    fn size(&self) -> usize {
+        match self {
+            Statement::Instruction(_, operands) => 1 + operands.len(),
+            Statement::Data(values) => values.len(),
+        }
+    }
+}
+
+fn is_identifier(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') &&
+        text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn split_operands(text: &str) -> Vec<&str> {
+    let text = text.trim();
+
+    if text.is_empty() {
+        Vec::new()
+    } else {
+        text.split(',').map(|operand| operand.trim()).collect()
+    }
+}
+
+fn parse_operand(line: usize, operand: &str) -> Result<(isize, Expression), AssemblerError> {
+    if let Some(value) = operand.strip_prefix('#') {
+        return Ok((1, Expression::parse(line, value)?));
+    }
+
+    if let Some(inner) = operand.strip_prefix('[').and_then(|operand| operand.strip_suffix(']')) {
+        let inner = inner.trim();
+
+        if inner == "rb" {
+            return Ok((2, Expression(vec![(1, Term::Number(0))])));
+        }
+
+        if let Some(offset) = inner.strip_prefix("rb") {
+            if offset.trim_start().starts_with(['+', '-']) {
+                return Ok((2, Expression::parse(line, offset)?));
+            }
+        }
+
+        return Ok((0, Expression::parse(line, inner)?));
+    }
+
+    Err(AssemblerError::InvalidOperand { line, operand: operand.to_string() })
+}
+
+fn parse_statement(line: usize, text: &str) -> Result<Statement, AssemblerError> {
+    let (word, rest) = match text.find(char::is_whitespace) {
+        Some(end) => (&text[..end], &text[end..]),
+        None => (text, "")
+    };
+
+    if let Some(directive) = word.strip_prefix('.') {
+        return match directive {
+            "data" => {
+                let values = split_operands(rest).iter()
+                    .map(|value| Expression::parse(line, value))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(Statement::Data(values))
+            },
+
+            _ => Err(AssemblerError::UnknownDirective { line, directive: word.to_string() })
+        };
+    }
+
+    let mnemonic = word.to_uppercase();
+    let (opcode, arity) = lookup(&mnemonic).ok_or_else(|| AssemblerError::UnknownMnemonic { line, mnemonic: word.to_string() })?;
+
+    let operands = match rest.split_once("->") {
+        Some((inputs, output)) => {
+            let mut operands = split_operands(inputs);
+            operands.push(output.trim());
+            operands
+        },
+        None => split_operands(rest)
+    };
+    if operands.len() != arity {
+        return Err(AssemblerError::WrongOperandCount { line, mnemonic, expected: arity, found: operands.len() });
+    }
+
+    let operands = operands.into_iter()
+        .map(|operand| parse_operand(line, operand))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Statement::Instruction(opcode, operands))
+}
+
+pub fn assemble(source: &str) -> Result<IntcodeProgram, AssemblerError> {
+    assemble_with_labels(source).map(|(program, _)| program)
+}
+
+pub fn assemble_with_labels(source: &str) -> Result<(IntcodeProgram, BTreeMap<String, usize>), AssemblerError> {
+    let mut labels = BTreeMap::new();
+    let mut statements = Vec::new();
+    let mut address = 0;
+
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let mut text = text.split(';').next().unwrap_or("").trim();
+
+        while let Some(colon) = text.find(':') {
+            let label = text[..colon].trim();
+
+            // Addresses from a disassembly listing (`0012:`) are accepted and ignored.
+            if !label.chars().all(|c| c.is_ascii_digit()) {
+                if !is_identifier(label) {
+                    return Err(AssemblerError::InvalidLabel { line, label: label.to_string() });
+                }
+
+                if labels.insert(label.to_string(), address).is_some() {
+                    return Err(AssemblerError::DuplicateLabel { line, label: label.to_string() });
+                }
+            }
+
+            text = text[colon+1..].trim();
+        }
+
+        if text.is_empty() {
+            continue;
+        }
+
+        let statement = parse_statement(line, text)?;
+        address += statement.size();
+        statements.push((line, statement));
+    }
+
+    let mut program = IntcodeProgram::with_capacity(address);
+
+    for (line, statement) in statements {
+        match statement {
+            Statement::Instruction(opcode, operands) => {
+                let mut instruction = opcode;
+                let mut values = Vec::new();
+
+                for (index, (mode, expression)) in operands.iter().enumerate() {
+                    let value = expression.evaluate(line, &labels)?;
+
+                    if *mode == 0 && value < 0 {
+                        return Err(AssemblerError::InvalidOperand { line, operand: format!("[{}]", value) });
+                    }
+
+                    instruction += mode * 10isize.pow(index as u32 + 2);
+                    values.push(value);
+                }
+
+                program.push(instruction);
+                program.extend(values);
+            },
+
+            Statement::Data(values) => {
+                for expression in values {
+                    program.push(expression.evaluate(line, &labels)?);
+                }
+            }
+        }
+    }
+
+    Ok((program, labels))
+}
+
+#[test]
+fn test_assemble_amplifier() {
+    let source = "
+        ; day 7 example: multiply the input signal by ten and add the phase
+                IN -> [phase]
+                IN -> [signal]
+                MUL [signal], #10 -> [signal]
+                ADD [signal], [phase] -> [phase]
+                OUT [phase]
+                HALT
+        phase:  .data 0
+        signal: .data 0
+    ";
+
+    assert_eq!(assemble(source), Ok(crate::intcode_parser("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0").unwrap()));
+}
+
+#[test]
+fn test_assemble_listing_round_trip() {
+    let program = crate::intcode_parser(include_str!("../../input/2019/day9.txt")).unwrap();
+
+    assert_eq!(assemble(&crate::listing(&program)), Ok(program));
+}
+
+#[test]
+fn test_assemble_errors() {
+    assert_eq!(assemble("ADD #1, #2"), Err(AssemblerError::WrongOperandCount {
+        line: 1, mnemonic: "ADD".to_string(), expected: 3, found: 2
+    }));
+
+    assert_eq!(assemble("HALT\n  JT #1, #missing"), Err(AssemblerError::UndefinedLabel {
+        line: 2, label: "missing".to_string()
+    }));
+
+    assert_eq!(assemble("\n\nloop: HALT\nloop: HALT").unwrap_err().line(), 4);
+    assert_eq!(assemble("OUT {5}").unwrap_err().line(), 1);
+    assert_eq!(assemble("HALT\n.data 9223372036854775807 + 1"), Err(AssemblerError::Overflow { line: 2 }));
+}
@@ -0,0 +1,115 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use crate::{Action, DenseMemory, IntcodeError, Machine, Memory, Word};
+
+// Runs a machine as a stream of its outputs. When the program needs input
+// it is taken from the `input` stream, suspending the machine until a value
+// arrives. The stream ends when the program halts, faults, runs out of
+// budget, or needs input after the input stream has ended.
+pub struct AsyncMachine<I, W: Word = isize, M: Memory<W> = DenseMemory<W>> {
+    machine: Machine<W, M>,
+    input: I,
+    fault: Option<IntcodeError<W>>,
+    halted: bool,
+    finished: bool
+}
+
+// The machine is never pinned in place, only polled through `&mut`.
+impl<I, W: Word, M: Memory<W>> Unpin for AsyncMachine<I, W, M> {}
+
+impl<I, W: Word, M: Memory<W>> AsyncMachine<I, W, M> {
+    pub fn new(machine: Machine<W, M>, input: I) -> Self {
+        AsyncMachine { machine, input, fault: None, halted: false, finished: false }
+    }
+
+    pub fn machine(&self) -> &Machine<W, M> {
+        &self.machine
+    }
+
+    pub fn into_machine(self) -> Machine<W, M> {
+        self.machine
+    }
+
+    pub fn fault(&self) -> Option<&IntcodeError<W>> {
+        self.fault.as_ref()
+    }
+
+    // Whether the stream ended because the program halted, rather than
+    // faulting, running out of budget or input.
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+}
+
+impl<W: Word, M: Memory<W>> Machine<W, M> {
+    pub fn into_async<I: Stream<Item = W> + Unpin>(self, input: I) -> AsyncMachine<I, W, M> {
+        AsyncMachine::new(self, input)
+    }
+}
+
+impl<I: Stream<Item = W> + Unpin, W: Word, M: Memory<W>> Stream for AsyncMachine<I, W, M> {
+    type Item = W;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<W>> {
+        let this = &mut *self;
+
+        while !this.finished {
+            match this.machine.try_run() {
+                Ok(Action::Output(value)) => return Poll::Ready(Some(value)),
+                Ok(Action::Halt) => {
+                    this.halted = true;
+                    this.finished = true;
+                },
+
+                // Nothing will wake the task to carry on, so the stream ends
+                // and the machine is left to be resumed by its owner.
+                Ok(Action::BudgetExhausted) => this.finished = true,
+
+                Ok(Action::RequiresInput) => match Pin::new(&mut this.input).poll_next(cx) {
+                    Poll::Ready(Some(value)) => this.machine.push_input(value),
+                    Poll::Ready(None) => this.finished = true,
+                    Poll::Pending => return Poll::Pending
+                },
+
+                Err(error) => {
+                    this.fault = Some(error);
+                    this.finished = true;
+                }
+            }
+        }
+
+        Poll::Ready(None)
+    }
+}
+
+#[test]
+fn test_amplifier_feedback_loop() {
+    use crate::{block_on, channel, intcode_parser, next, IntcodeProgram};
+
+    let program: IntcodeProgram = intcode_parser("3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5").unwrap();
+
+    let (feedback, input) = channel();
+    feedback.send(0);
+
+    let mut amplifiers: Box<dyn Stream<Item = isize> + Unpin> = Box::new(input);
+    for phase in [9, 8, 7, 6, 5].iter() {
+        let mut machine = Machine::new(&program);
+        machine.push_input(*phase);
+        amplifiers = Box::new(machine.into_async(amplifiers));
+    }
+
+    let thrusters = block_on(async {
+        let mut last = None;
+        while let Some(value) = next(&mut amplifiers).await {
+            feedback.send(value);
+            last = Some(value);
+        }
+
+        last
+    });
+
+    assert_eq!(thrusters, Some(139629729));
+}
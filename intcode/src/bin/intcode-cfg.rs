@@ -0,0 +1,32 @@
+use std::{env, fs, process};
+
+use intcode::*;
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: intcode-cfg <program>");
+            process::exit(1);
+        }
+    };
+
+    let input = match fs::read_to_string(&path) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("Could not read {}: {}", path, error);
+            process::exit(1);
+        }
+    };
+
+    let program = match intcode_parser::<isize>(&input) {
+        Ok(program) => program,
+        Err(error) => {
+            let (line, column) = error.position(&input);
+            eprintln!("{}:{}:{}: {}", path, line, column, error);
+            process::exit(1);
+        }
+    };
+
+    print!("{}", Cfg::build(&program).to_dot());
+}
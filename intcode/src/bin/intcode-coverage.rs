@@ -0,0 +1,65 @@
+use std::{env, fs, process};
+
+use intcode::*;
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+// Runs the program once per input set and prints the merged coverage.
+fn main() {
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+
+    let json = args.first().map(|arg| arg == "--json").unwrap_or(false);
+    if json {
+        args.remove(0);
+    }
+
+    let path = match args.first() {
+        Some(path) => path,
+        None => fail("Usage: intcode-coverage [--json] <program> [inputs,...]...".to_string())
+    };
+
+    let program: IntcodeProgram = match fs::read_to_string(path) {
+        Ok(input) => intcode_parser(&input).unwrap_or_else(|error| {
+            let (line, column) = error.position(&input);
+            fail(format!("{}:{}:{}: {}", path, line, column, error))
+        }),
+        Err(error) => fail(format!("Could not read {}: {}", path, error))
+    };
+
+    // No input sets still means one run, without input.
+    let runs = if args.len() > 1 { args[1..].to_vec() } else { vec![String::new()] };
+    let mut coverage = Coverage::default();
+
+    for run in &runs {
+        let mut machine = Machine::new(&program);
+        machine.start_coverage();
+
+        for input in run.split(',').filter(|input| !input.is_empty()) {
+            match input.trim().parse() {
+                Ok(value) => machine.push_input(value),
+                Err(_) => fail(format!("Not a number: {}", input))
+            }
+        }
+
+        loop {
+            match machine.try_run() {
+                Ok(Action::Output(value)) => eprintln!("[{}] Output: {}", run, value),
+                Ok(Action::RequiresInput) => { eprintln!("[{}] Program requires more input, stopping", run); break; },
+                Ok(Action::Halt) => break,
+                Ok(Action::BudgetExhausted) => { eprintln!("[{}] Instruction budget exhausted, stopping", run); break; },
+                Err(error) => { eprintln!("[{}] Fault: {}", run, error); break; }
+            }
+        }
+
+        coverage.merge(&machine.take_coverage().expect("Coverage was not enabled"));
+    }
+
+    if json {
+        println!("{}", coverage.to_json(&program));
+    } else {
+        print!("{}", coverage.listing(&program));
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::{env, fs, process};
+
+use intcode::*;
+
+const HELP: &str = "\
+Commands:
+  break <addr>         set a breakpoint (alias: b)
+  delete <addr>        remove a breakpoint
+  breakpoints          list breakpoints
+  step [n]             execute n instructions, default 1 (alias: s)
+  continue             run to the next action or breakpoint (alias: c)
+  back [n]             step n instructions backwards, default 1 (alias: sb)
+  rwrite <addr>        run backwards to the last write of an address
+  routput              run backwards to the last output
+  list [addr] [n]      disassemble n instructions from addr, default the IP (alias: l)
+  mem <addr> [n]       show n memory cells from addr, default 1 (alias: x)
+  set <addr> <value>   write a value to memory
+  rb [value]           show or set the relative base
+  input <values...>    queue input values (alias: i)
+  outputs              show outputs produced within the history limit
+  save <file>          save the machine state to a file
+  load <file>          restore the machine state from a file
+  info                 show IP, relative base and pending inputs
+  help                 show this message
+  quit                 exit the debugger (alias: q)";
+
+const USAGE: &str = "Usage: intcode-dbg [--history <steps>] <program>";
+
+// How many steps `back` can undo unless `--history` says otherwise.
+const HISTORY_LIMIT: usize = 1_000_000;
+
+enum Stop {
+    Action(Action),
+    Breakpoint,
+    Steps,
+    Fault(IntcodeError)
+}
+
+struct Debugger {
+    machine: Machine,
+    breakpoints: BTreeSet<usize>,
+    halted: bool
+}
+
+impl Debugger {
+    fn new(program: &IntcodeProgram, history_limit: usize) -> Self {
+        let mut machine = Machine::new(program);
+        machine.start_history(Some(history_limit));
+
+        Debugger {
+            machine,
+            breakpoints: BTreeSet::new(),
+            halted: false
+        }
+    }
+
+    fn step(&mut self) -> Option<Stop> {
+        if self.halted {
+            return Some(Stop::Action(Action::Halt));
+        }
+
+        match self.machine.try_step() {
+            Ok(Some(action)) => {
+                self.halted = action == Action::Halt;
+                Some(Stop::Action(action))
+            },
+
+            Ok(None) => None,
+            Err(error) => Some(Stop::Fault(error))
+        }
+    }
+
+    fn run(&mut self, limit: Option<usize>) -> Stop {
+        let mut count = 0;
+
+        loop {
+            if let Some(stop) = self.step() {
+                return stop;
+            }
+
+            count += 1;
+            if limit == Some(count) {
+                return Stop::Steps;
+            }
+
+            if self.breakpoints.contains(&self.machine.ip()) {
+                return Stop::Breakpoint;
+            }
+        }
+    }
+
+    fn report(&self, stop: Stop) {
+        match stop {
+            Stop::Action(Action::Output(value)) => println!("Output: {}", value),
+            Stop::Action(Action::RequiresInput) => println!("Waiting for input"),
+            Stop::Action(Action::Halt) => println!("Halted"),
+            Stop::Action(Action::BudgetExhausted) => println!("Instruction budget exhausted"),
+            Stop::Breakpoint => println!("Breakpoint at {:04}", self.machine.ip()),
+            Stop::Steps => {},
+            Stop::Fault(error) => println!("Fault: {}", error)
+        }
+
+        if !self.halted {
+            self.list(self.machine.ip(), 1);
+        }
+    }
+
+    fn outputs(&self) -> Vec<isize> {
+        self.machine.history().map(|history| history.outputs()).unwrap_or_default()
+    }
+
+    fn list(&self, mut address: usize, count: usize) {
+        let memory = self.machine.memory();
+
+        for _ in 0..count {
+            if address >= memory.extent() {
+                break;
+            }
+
+            let marker = if address == self.machine.ip() { "=>" } else if self.breakpoints.contains(&address) { " *" } else { "  " };
+
+            match Instruction::decode(memory, address) {
+                Ok(instruction) => {
+                    println!("{} {:04}: {}", marker, address, instruction);
+                    address += instruction.size();
+                },
+                Err(_) => {
+                    println!("{} {:04}: .data {}", marker, address, memory[address]);
+                    address += 1;
+                }
+            }
+        }
+    }
+
+    fn execute(&mut self, command: &str, arguments: &[&str]) -> Result<bool, String> {
+        let number = |index: usize| -> Result<Option<isize>, String> {
+            arguments.get(index)
+                .map(|argument| argument.parse::<isize>().map_err(|_| format!("Not a number: {}", argument)))
+                .transpose()
+        };
+
+        let address = |index: usize| -> Result<Option<usize>, String> {
+            match number(index)? {
+                Some(value) if value < 0 => Err(format!("Not an address: {}", value)),
+                value => Ok(value.map(|value| value as usize))
+            }
+        };
+
+        match command {
+            "break" | "b" => {
+                let address = address(0)?.ok_or("Usage: break <addr>")?;
+                self.breakpoints.insert(address);
+                println!("Breakpoint set at {:04}", address);
+            },
+
+            "delete" => {
+                let address = address(0)?.ok_or("Usage: delete <addr>")?;
+                if !self.breakpoints.remove(&address) {
+                    return Err(format!("No breakpoint at {:04}", address));
+                }
+            },
+
+            "breakpoints" => {
+                for address in &self.breakpoints {
+                    self.list(*address, 1);
+                }
+            },
+
+            "step" | "s" => {
+                let count = address(0)?.unwrap_or(1);
+                if count > 0 {
+                    let stop = self.run(Some(count));
+                    self.report(stop);
+                }
+            },
+
+            "continue" | "c" => {
+                let stop = self.run(None);
+                self.report(stop);
+            },
+
+            "back" | "sb" => {
+                let count = address(0)?.unwrap_or(1);
+                for _ in 0..count {
+                    if !self.machine.step_back() {
+                        println!("Reached the start of the history");
+                        break;
+                    }
+                }
+
+                self.halted = false;
+                self.list(self.machine.ip(), 1);
+            },
+
+            "rwrite" => {
+                let address = address(0)?.ok_or("Usage: rwrite <addr>")?;
+                if !self.machine.run_back_to_write(address) {
+                    println!("No write to {:04} in the history", address);
+                }
+
+                self.halted = false;
+                self.list(self.machine.ip(), 1);
+            },
+
+            "routput" => {
+                match self.machine.rewind_to_output() {
+                    Some(value) => println!("Output: {}", value),
+                    None => println!("No output in the history")
+                }
+
+                self.halted = false;
+                self.list(self.machine.ip(), 1);
+            },
+
+            "list" | "l" => {
+                let start = address(0)?.unwrap_or_else(|| self.machine.ip());
+                self.list(start, address(1)?.unwrap_or(10));
+            },
+
+            "mem" | "x" => {
+                let start = address(0)?.ok_or("Usage: mem <addr> [n]")?;
+                let count = address(1)?.unwrap_or(1);
+
+                for address in start..start+count {
+                    println!("{:04}: {}", address, self.machine.memory().read(address));
+                }
+            },
+
+            "set" => {
+                let address = address(0)?;
+                let value = number(1)?;
+
+                match (address, value) {
+                    (Some(address), Some(value)) => self.machine.write(value, &Parameter::Position(address)),
+                    _ => return Err("Usage: set <addr> <value>".to_string())
+                }
+            },
+
+            "rb" => {
+                if let Some(value) = number(0)? {
+                    self.machine.set_relative_base(value);
+                }
+
+                println!("Relative base: {}", self.machine.relative_base());
+            },
+
+            "input" | "i" => {
+                for index in 0..arguments.len() {
+                    if let Some(value) = number(index)? {
+                        self.machine.push_input(value);
+                    }
+                }
+            },
+
+            "outputs" => println!("{:?}", self.outputs()),
+
+            "save" => {
+                let path = arguments.first().ok_or("Usage: save <file>")?;
+                let mut file = File::create(path).map_err(|error| error.to_string())?;
+                self.machine.snapshot().save(&mut file).map_err(|error| error.to_string())?;
+                println!("Saved to {}", path);
+            },
+
+            "load" => {
+                let path = arguments.first().ok_or("Usage: load <file>")?;
+                let file = File::open(path).map_err(|error| error.to_string())?;
+                let snapshot = Snapshot::load(BufReader::new(file)).map_err(|error| error.to_string())?;
+
+                self.machine.restore(&snapshot);
+                self.halted = false;
+                self.list(self.machine.ip(), 1);
+            },
+
+            "info" => {
+                println!("IP: {:04}", self.machine.ip());
+                println!("Relative base: {}", self.machine.relative_base());
+                println!("Pending inputs: {:?}", self.machine.inputs());
+                println!("Outputs: {}", self.outputs().len());
+            },
+
+            "help" | "h" | "?" => println!("{}", HELP),
+            "quit" | "q" => return Ok(false),
+
+            _ => return Err(format!("Unknown command '{}', try 'help'", command))
+        }
+
+        Ok(true)
+    }
+}
+
+fn main() {
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    let usage = || -> ! {
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    };
+
+    let history_limit = match args.first().map(|arg| arg.as_str()) {
+        Some("--history") => {
+            let limit = args.get(1).and_then(|limit| limit.parse().ok()).unwrap_or_else(|| usage());
+            args.drain(..2);
+            limit
+        },
+
+        _ => HISTORY_LIMIT
+    };
+
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage()
+    };
+
+    let input = match fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("Could not read {}: {}", path, error);
+            process::exit(1);
+        }
+    };
+
+    let program = match intcode_parser::<isize>(&input) {
+        Ok(program) => program,
+        Err(error) => {
+            let (line, column) = error.position(&input);
+            eprintln!("{}:{}:{}: {}", path, line, column, error);
+            process::exit(1);
+        }
+    };
+
+    let mut debugger = Debugger::new(&program, history_limit);
+    debugger.list(0, 1);
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        print!("(dbg) ");
+        io::stdout().flush().expect("Could not flush stdout");
+
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break
+        };
+
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let (command, arguments) = match words.split_first() {
+            Some((command, arguments)) => (*command, arguments),
+            None => continue
+        };
+
+        match debugger.execute(command, arguments) {
+            Ok(true) => {},
+            Ok(false) => break,
+            Err(message) => println!("{}", message)
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::{env, fs, process};
+
+use intcode::*;
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+// Converts a text program to the binary format, or a binary one back to text.
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let (source, destination) = match args.as_slice() {
+        [source, destination] => (source, destination),
+        _ => fail("Usage: intcode-pack <input> <output>".to_string())
+    };
+
+    let data = match fs::read(source) {
+        Ok(data) => data,
+        Err(error) => fail(format!("Could not read {}: {}", source, error))
+    };
+
+    let output = if BinaryProgram::<isize>::is_binary(&data) {
+        match BinaryProgram::<isize>::read(&mut &data[..]) {
+            Ok(binary) => binary.to_text().into_bytes(),
+            Err(error) => fail(format!("Could not read {}: {}", source, error))
+        }
+    } else {
+        let input = String::from_utf8_lossy(&data);
+        let binary = BinaryProgram::<isize>::from_text(&input).unwrap_or_else(|error| {
+            let (line, column) = error.position(&input);
+            fail(format!("{}:{}:{}: {}", source, line, column, error))
+        });
+
+        let mut output = Vec::new();
+        binary.write(&mut output).expect("Writing to memory cannot fail");
+        output
+    };
+
+    if let Err(error) = fs::write(destination, output) {
+        fail(format!("Could not write {}: {}", destination, error));
+    }
+}
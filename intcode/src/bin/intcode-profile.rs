@@ -0,0 +1,48 @@
+use std::{env, fs, process};
+
+use intcode::*;
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn main() {
+    let args = env::args().collect::<Vec<_>>();
+
+    let path = match args.get(1) {
+        Some(path) => path,
+        None => fail("Usage: intcode-profile <program> [inputs...]".to_string())
+    };
+
+    let program: IntcodeProgram = match fs::read_to_string(path) {
+        Ok(input) => intcode_parser(&input).unwrap_or_else(|error| {
+            let (line, column) = error.position(&input);
+            fail(format!("{}:{}:{}: {}", path, line, column, error))
+        }),
+        Err(error) => fail(format!("Could not read {}: {}", path, error))
+    };
+
+    let mut machine = Machine::new(&program);
+    machine.start_profile();
+
+    for input in &args[2..] {
+        match input.parse() {
+            Ok(value) => machine.push_input(value),
+            Err(_) => fail(format!("Not a number: {}", input))
+        }
+    }
+
+    loop {
+        match machine.try_run() {
+            Ok(Action::Output(value)) => eprintln!("Output: {}", value),
+            Ok(Action::RequiresInput) => { eprintln!("Program requires more input, stopping"); break; },
+            Ok(Action::Halt) => break,
+            Ok(Action::BudgetExhausted) => { eprintln!("Instruction budget exhausted, stopping"); break; },
+            Err(error) => { eprintln!("Fault: {}", error); break; }
+        }
+    }
+
+    let profile = machine.take_profile().expect("Profiling was not enabled");
+    print!("{}", profile.report(&program));
+}
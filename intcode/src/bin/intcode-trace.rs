@@ -0,0 +1,81 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::{env, process};
+
+use intcode::*;
+
+const USAGE: &str = "\
+Usage:
+  intcode-trace record <program> <trace> [inputs...]
+  intcode-trace replay <program> <trace>";
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn load_program(path: &str) -> IntcodeProgram {
+    match fs::read_to_string(path) {
+        Ok(input) => intcode_parser(&input).unwrap_or_else(|error| {
+            let (line, column) = error.position(&input);
+            fail(format!("{}:{}:{}: {}", path, line, column, error))
+        }),
+        Err(error) => fail(format!("Could not read {}: {}", path, error))
+    }
+}
+
+fn record(program: &IntcodeProgram, path: &str, inputs: &[String]) {
+    let mut machine = Machine::new(program);
+    machine.start_trace();
+
+    for input in inputs {
+        match input.parse() {
+            Ok(value) => machine.push_input(value),
+            Err(_) => fail(format!("Not a number: {}", input))
+        }
+    }
+
+    loop {
+        match machine.try_run() {
+            Ok(Action::Output(value)) => println!("{}", value),
+            Ok(Action::RequiresInput) => { eprintln!("Program requires more input, stopping"); break; },
+            Ok(Action::Halt) => break,
+            Ok(Action::BudgetExhausted) => { eprintln!("Instruction budget exhausted, stopping"); break; },
+            Err(error) => { eprintln!("Fault: {}", error); break; }
+        }
+    }
+
+    let trace = machine.take_trace().expect("Tracing was not enabled");
+    let result = File::create(path).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        trace.write(&mut writer)?;
+        writer.flush()
+    });
+
+    match result {
+        Ok(()) => eprintln!("Recorded {} events to {}", trace.events.len(), path),
+        Err(error) => fail(format!("Could not write {}: {}", path, error))
+    }
+}
+
+fn replay_trace(program: &IntcodeProgram, path: &str) {
+    let trace = match File::open(path).and_then(|file| Trace::read(&mut BufReader::new(file))) {
+        Ok(trace) => trace,
+        Err(error) => fail(format!("Could not read {}: {}", path, error))
+    };
+
+    match replay(program, &trace) {
+        Ok(()) => println!("Replayed {} events without divergence", trace.events.len()),
+        Err(divergence) => fail(divergence.to_string())
+    }
+}
+
+fn main() {
+    let args = env::args().collect::<Vec<_>>();
+
+    match (args.get(1).map(|s| s.as_str()), args.get(2), args.get(3)) {
+        (Some("record"), Some(program), Some(trace)) => record(&load_program(program), trace, &args[4..]),
+        (Some("replay"), Some(program), Some(trace)) => replay_trace(&load_program(program), trace),
+        _ => fail(USAGE.to_string())
+    }
+}
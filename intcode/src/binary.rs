@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+use crate::encoding::{invalid_data, read_varint, write_varint};
+use crate::{intcode_parser, IntcodeProgram, ParseError, Word};
+
+const BINARY_MAGIC: &[u8; 4] = b"ICPG";
+const BINARY_VERSION: u8 = 1;
+
+const HAS_SYMBOLS: u8 = 1;
+
+// A program in the compact binary format, along with any labels it was
+// assembled from.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BinaryProgram<W = isize> {
+    pub program: IntcodeProgram<W>,
+    pub symbols: BTreeMap<String, usize>
+}
+
+impl<W: Word> BinaryProgram<W> {
+    pub fn new(program: IntcodeProgram<W>) -> Self {
+        BinaryProgram { program, symbols: BTreeMap::new() }
+    }
+
+    pub fn with_symbols(program: IntcodeProgram<W>, symbols: BTreeMap<String, usize>) -> Self {
+        BinaryProgram { program, symbols }
+    }
+
+    // Whether `data` starts like a binary program rather than text.
+    pub fn is_binary(data: &[u8]) -> bool {
+        data.starts_with(BINARY_MAGIC)
+    }
+
+    pub fn from_text(input: &str) -> Result<Self, ParseError> {
+        intcode_parser(input).map(BinaryProgram::new)
+    }
+
+    // The comma-separated text form. Symbols have no place in it and are
+    // left out.
+    pub fn to_text(&self) -> String {
+        let values = self.program.iter().map(|value| value.to_string()).collect::<Vec<_>>();
+        format!("{}\n", values.join(","))
+    }
+
+    // The header is the magic, a version byte and a flags byte, followed by
+    // the word count and the words as zigzag varints. If the flags say so, a
+    // symbol table follows: a count, then each name as a length-prefixed
+    // UTF-8 string and its address.
+    pub fn write<Wr: Write>(&self, writer: &mut Wr) -> io::Result<()> {
+        let flags = if self.symbols.is_empty() { 0 } else { HAS_SYMBOLS };
+
+        writer.write_all(BINARY_MAGIC)?;
+        writer.write_all(&[BINARY_VERSION, flags])?;
+
+        write_varint(writer, self.program.len() as u128)?;
+        for value in &self.program {
+            value.encode(writer)?;
+        }
+
+        if flags & HAS_SYMBOLS != 0 {
+            write_varint(writer, self.symbols.len() as u128)?;
+
+            for (name, address) in &self.symbols {
+                write_varint(writer, name.len() as u128)?;
+                writer.write_all(name.as_bytes())?;
+                write_varint(writer, *address as u128)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<BinaryProgram<W>> {
+        let mut header = [0; 6];
+        reader.read_exact(&mut header)?;
+
+        if &header[..4] != BINARY_MAGIC {
+            return Err(invalid_data("not an intcode program file"));
+        }
+
+        if header[4] != BINARY_VERSION {
+            return Err(invalid_data(&format!("unsupported program version {}", header[4])));
+        }
+
+        let flags = header[5];
+        if flags & !HAS_SYMBOLS != 0 {
+            return Err(invalid_data(&format!("unknown program flags {:#04x}", flags)));
+        }
+
+        // Grown as values arrive, so a corrupt count cannot ask for a huge
+        // allocation up front.
+        let length = read_length(reader)?;
+        let mut program = Vec::new();
+        for _ in 0..length {
+            program.push(W::decode(reader)?);
+        }
+
+        let mut symbols = BTreeMap::new();
+        if flags & HAS_SYMBOLS != 0 {
+            for _ in 0..read_length(reader)? {
+                let length = read_length(reader)?;
+                let mut name = Vec::new();
+                if reader.by_ref().take(length as u64).read_to_end(&mut name)? != length {
+                    return Err(invalid_data("symbol name is cut short"));
+                }
+
+                let name = String::from_utf8(name).map_err(|_| invalid_data("symbol name is not UTF-8"))?;
+                symbols.insert(name, read_length(reader)?);
+            }
+        }
+
+        if reader.read(&mut [0])? != 0 {
+            return Err(invalid_data("trailing data after program"));
+        }
+
+        Ok(BinaryProgram { program, symbols })
+    }
+}
+
+fn read_length<R: Read>(reader: &mut R) -> io::Result<usize> {
+    usize::try_from(read_varint(reader)?).map_err(|_| invalid_data("length is out of range"))
+}
+
+#[test]
+fn test_binary_round_trip() {
+    let (program, symbols) = crate::assemble_with_labels("
+                IN -> [value]
+        loop:   ADD [value], #-1 -> [value]
+                JT [value], #loop
+                HALT
+        value:  .data -123456789
+    ").unwrap();
+
+    let binary = BinaryProgram::with_symbols(program.clone(), symbols);
+    let mut encoded = Vec::new();
+    binary.write(&mut encoded).unwrap();
+
+    assert!(BinaryProgram::<isize>::is_binary(&encoded));
+    assert_eq!(BinaryProgram::read(&mut &encoded[..]).unwrap(), binary);
+
+    let text = BinaryProgram::new(program).to_text();
+    assert_eq!(BinaryProgram::<isize>::from_text(&text).unwrap().program, binary.program);
+
+    encoded.push(0);
+    assert!(BinaryProgram::<isize>::read(&mut &encoded[..]).is_err());
+    assert!(BinaryProgram::<isize>::read(&mut text.as_bytes()).is_err());
+}
+
+#[test]
+fn test_binary_is_compact() {
+    let text = include_str!("../../input/2019/day9.txt");
+    let mut encoded = Vec::new();
+    BinaryProgram::<isize>::from_text(text).unwrap().write(&mut encoded).unwrap();
+
+    assert!(encoded.len() * 2 < text.len());
+    assert_eq!(BinaryProgram::<isize>::read(&mut &encoded[..]).unwrap().to_text().trim(), text.trim());
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use crate::{Instruction, Machine, Memory, Word};
+
+// The longest instruction is an opcode plus three parameters.
+const MAX_INSTRUCTION_SIZE: usize = 4;
+
+// Code running at very high addresses (only possible with sparse memory) is
+// decoded every time rather than growing the cache to match.
+const MAX_CACHED_ADDRESS: usize = 1 << 20;
+
+#[derive(Clone, Debug)]
+pub(crate) struct InstructionCache<W> {
+    // Decoded up front by `warm_instruction_cache` and shared by every clone
+    // of the machine, which each mark the entries their own writes make stale.
+    shared: Arc<Vec<Option<Instruction<W>>>>,
+    stale: Vec<bool>,
+    entries: Vec<Option<Instruction<W>>>,
+    enabled: bool
+}
+
+impl<W: Word> InstructionCache<W> {
+    pub fn new() -> Self {
+        InstructionCache { shared: Arc::new(Vec::new()), stale: Vec::new(), entries: Vec::new(), enabled: true }
+    }
+
+    pub fn get(&self, address: usize) -> Option<&Instruction<W>> {
+        if let Some(Some(instruction)) = self.entries.get(address) {
+            return Some(instruction);
+        }
+
+        if self.is_stale(address) {
+            return None;
+        }
+
+        self.shared.get(address)?.as_ref()
+    }
+
+    fn is_stale(&self, address: usize) -> bool {
+        self.stale.get(address).copied().unwrap_or(false)
+    }
+
+    pub fn insert(&mut self, address: usize, instruction: Instruction<W>) {
+        if !self.enabled || address >= MAX_CACHED_ADDRESS {
+            return;
+        }
+
+        if address >= self.entries.len() {
+            self.entries.resize(address + 1, None);
+        }
+
+        self.entries[address] = Some(instruction);
+    }
+
+    // Drops any cached instruction whose encoding covers `address`.
+    pub fn invalidate(&mut self, address: usize) {
+        let first = address.saturating_sub(MAX_INSTRUCTION_SIZE - 1);
+
+        for start in first..=address {
+            let covers = |instruction: &Instruction<W>| start + instruction.size() > address;
+
+            if let Some(entry) = self.entries.get_mut(start) {
+                if entry.as_ref().is_some_and(covers) {
+                    *entry = None;
+                }
+            }
+
+            if !self.is_stale(start) && self.shared.get(start).and_then(Option::as_ref).is_some_and(covers) {
+                if start >= self.stale.len() {
+                    self.stale.resize(start + 1, false);
+                }
+
+                self.stale[start] = true;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.shared = Arc::new(Vec::new());
+        self.stale.clear();
+        self.entries.clear();
+    }
+}
+
+impl<W: Word, M: Memory<W>> Machine<W, M> {
+    // The cache is on by default; turning it off decodes every instruction
+    // from memory as it is executed.
+    pub fn set_instruction_cache(&mut self, enabled: bool) {
+        self.cache.enabled = enabled;
+        self.cache.clear();
+    }
+
+    // Decodes the whole of memory up front. Clones of the machine share what
+    // was decoded, so machines cloned from one prepared this way skip
+    // decoding altogether until they overwrite their code.
+    pub fn warm_instruction_cache(&mut self) {
+        if !self.cache.enabled {
+            return;
+        }
+
+        let decoded = (0..self.memory.extent().min(MAX_CACHED_ADDRESS))
+            .map(|address| Instruction::decode(&self.memory, address).ok())
+            .collect();
+
+        self.cache.clear();
+        self.cache.shared = Arc::new(decoded);
+    }
+}
+
+#[test]
+fn test_self_modifying_program() {
+    use crate::{assemble, Action};
+
+    let program = assemble("
+        start:  OUT #7
+                ADD [start+1], #1 -> [start+1]
+                ADD [count], #1 -> [count]
+                EQ [count], #3 -> [done]
+                JF [done], #start
+                HALT
+        count:  .data 0
+        done:   .data 0
+    ").unwrap();
+
+    for (enabled, warm) in [(true, false), (true, true), (false, false)] {
+        let mut template = Machine::new(&program);
+        template.set_instruction_cache(enabled);
+        if warm {
+            template.warm_instruction_cache();
+        }
+
+        // The clone patches its code first; the template must not see it.
+        for mut machine in [template.clone(), template] {
+            let mut outputs = Vec::new();
+
+            while let Action::Output(value) = machine.run() {
+                outputs.push(value);
+            }
+
+            assert_eq!(outputs, vec![7, 8, 9]);
+        }
+    }
+}
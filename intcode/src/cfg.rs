@@ -0,0 +1,322 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+
+use crate::{DenseMemory, Instruction, IntcodeProgram, Memory, Parameter, Word};
+
+// Where a jump goes: a constant address, back to the caller through the
+// return address on the relative-base stack, or somewhere only known at runtime.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Target {
+    Address(usize),
+    Return,
+    Unknown
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Exit {
+    // Runs straight into the block at the given address.
+    Fallthrough(usize),
+    Jump(Target),
+    // A conditional jump, with the address reached when it is not taken.
+    Branch(Target, usize),
+    // An unconditional jump made after storing the address of the next
+    // instruction in a relative-base slot, the compiled subroutine call idiom.
+    Call { target: usize, return_to: usize },
+    Halt,
+    // Runs into a word that does not decode as an instruction.
+    Invalid
+}
+
+impl Exit {
+    pub fn successors(&self) -> Vec<usize> {
+        match *self {
+            Exit::Fallthrough(next) => vec![next],
+            Exit::Jump(Target::Address(target)) => vec![target],
+            Exit::Jump(_) => vec![],
+            Exit::Branch(Target::Address(target), next) => vec![target, next],
+            Exit::Branch(_, next) => vec![next],
+            Exit::Call { target, return_to } => vec![target, return_to],
+            Exit::Halt | Exit::Invalid => vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block<W = isize> {
+    pub start: usize,
+    pub instructions: Vec<(usize, Instruction<W>)>,
+    pub exit: Exit
+}
+
+impl<W: Word> Block<W> {
+    // One past the last instruction of the block.
+    pub fn end(&self) -> usize {
+        self.instructions.last()
+            .map(|(address, instruction)| address + instruction.size())
+            .unwrap_or(self.start)
+    }
+}
+
+// The basic blocks reachable from address 0, found by following every
+// statically known jump target. Code only reached through computed jumps
+// is left out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cfg<W = isize> {
+    blocks: BTreeMap<usize, Block<W>>
+}
+
+impl<W: Word> Cfg<W> {
+    pub fn build(program: &IntcodeProgram<W>) -> Self {
+        let memory = DenseMemory::from_program(program);
+        let mut instructions = BTreeMap::new();
+        let mut exits = HashMap::new();
+        let mut leaders = BTreeSet::from([0]);
+        let mut pending = vec![0];
+
+        while let Some(start) = pending.pop() {
+            let mut address = start;
+            let mut run = Vec::new();
+
+            loop {
+                if instructions.contains_key(&address) {
+                    leaders.insert(address);
+                    break;
+                }
+
+                let instruction = match Instruction::decode(&memory, address) {
+                    Ok(instruction) if address + instruction.size() <= program.len() => instruction,
+                    _ => break
+                };
+
+                let next = address + instruction.size();
+                let exit = block_exit(&instruction, &run, next, program.len());
+                instructions.insert(address, instruction.clone());
+                run.push(instruction);
+
+                if let Some(exit) = exit {
+                    for successor in exit.successors() {
+                        if leaders.insert(successor) {
+                            pending.push(successor);
+                        }
+                    }
+
+                    exits.insert(address, exit);
+                    break;
+                }
+
+                address = next;
+            }
+        }
+
+        let mut blocks = BTreeMap::new();
+
+        for &start in &leaders {
+            let mut body = Vec::new();
+            let mut address = start;
+
+            let exit = loop {
+                let instruction = match instructions.get(&address) {
+                    Some(instruction) => instruction,
+                    None => break Exit::Invalid
+                };
+
+                body.push((address, instruction.clone()));
+                let next = address + instruction.size();
+
+                if let Some(exit) = exits.get(&address) {
+                    break *exit;
+                }
+
+                if leaders.contains(&next) {
+                    break Exit::Fallthrough(next);
+                }
+
+                address = next;
+            };
+
+            blocks.insert(start, Block { start, instructions: body, exit });
+        }
+
+        Cfg { blocks }
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = &Block<W>> {
+        self.blocks.values()
+    }
+
+    pub fn block(&self, start: usize) -> Option<&Block<W>> {
+        self.blocks.get(&start)
+    }
+
+    // Entry points of the subroutines called from anywhere in the program.
+    pub fn subroutines(&self) -> BTreeSet<usize> {
+        self.blocks()
+            .filter_map(|block| match block.exit {
+                Exit::Call { target, .. } => Some(target),
+                _ => None
+            })
+            .collect()
+    }
+
+    // The graph in Graphviz format, one box per block listing its instructions.
+    // Subroutine entries get a double border, and calls a bold edge to the
+    // subroutine next to a dashed one to the return address.
+    pub fn to_dot(&self) -> String {
+        let subroutines = self.subroutines();
+        let mut dot = String::new();
+
+        writeln!(dot, "digraph intcode {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+
+        for block in self.blocks() {
+            let mut label = String::new();
+            for (address, instruction) in &block.instructions {
+                write!(label, "{:04}: {}\\l", address, instruction).unwrap();
+            }
+
+            match block.exit {
+                Exit::Jump(Target::Return) | Exit::Branch(Target::Return, _) => label.push_str("(return)\\l"),
+                Exit::Jump(Target::Unknown) | Exit::Branch(Target::Unknown, _) => label.push_str("(computed jump)\\l"),
+                Exit::Invalid => label.push_str("(invalid instruction)\\l"),
+                _ => {}
+            }
+
+            let peripheries = if subroutines.contains(&block.start) { ", peripheries=2" } else { "" };
+            writeln!(dot, "    b{} [label=\"{}\"{}];", block.start, label.replace('"', "\\\""), peripheries).unwrap();
+        }
+
+        for block in self.blocks() {
+            let edges = match block.exit {
+                Exit::Fallthrough(next) => vec![(next, "")],
+                Exit::Jump(Target::Address(target)) => vec![(target, "")],
+                Exit::Branch(Target::Address(target), next) => vec![(target, " [label=\"taken\"]"), (next, " [label=\"not taken\"]")],
+                Exit::Branch(_, next) => vec![(next, " [label=\"not taken\"]")],
+                Exit::Call { target, return_to } => vec![(target, " [label=\"call\", style=bold]"), (return_to, " [label=\"return\", style=dashed]")],
+                _ => vec![]
+            };
+
+            for (successor, attributes) in edges {
+                writeln!(dot, "    b{} -> b{}{};", block.start, successor, attributes).unwrap();
+            }
+        }
+
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+// The exit of a block ending in `instruction`, or None if the instruction
+// does not end a block. `run` holds the instructions leading up to it.
+fn block_exit<W: Word>(instruction: &Instruction<W>, run: &[Instruction<W>], next: usize, len: usize) -> Option<Exit> {
+    let (condition, target, jump_if_zero) = match instruction {
+        Instruction::JumpIfTrue(condition, target) => (condition, target, false),
+        Instruction::JumpIfFalse(condition, target) => (condition, target, true),
+        Instruction::Halt => return Some(Exit::Halt),
+        _ => return None
+    };
+
+    let target = match target {
+        Parameter::Immediate(value) => match value.to_usize() {
+            Some(address) if address < len => Target::Address(address),
+            _ => Target::Unknown
+        },
+
+        // The call idiom leaves the return address at or above the relative
+        // base. A slot the block has just written holds something else.
+        Parameter::Relative(offset) if !offset.is_negative() && !run.iter().any(|instruction| instruction.output() == Some(target)) =>
+            Target::Return,
+
+        Parameter::Relative(_) | Parameter::Position(_) => Target::Unknown
+    };
+
+    let taken = match condition {
+        Parameter::Immediate(value) => value.is_zero() == jump_if_zero,
+        _ => return Some(Exit::Branch(target, next))
+    };
+
+    if !taken {
+        return Some(Exit::Fallthrough(next));
+    }
+
+    match target {
+        Target::Address(target) if run.iter().any(|instruction| stores_return_address(instruction, next)) =>
+            Some(Exit::Call { target, return_to: next }),
+
+        _ => Some(Exit::Jump(target))
+    }
+}
+
+// Whether `instruction` writes the constant `address` to a relative-base slot.
+pub(crate) fn stores_return_address<W: Word>(instruction: &Instruction<W>, address: usize) -> bool {
+    let value = match instruction {
+        Instruction::Add(Parameter::Immediate(lhs), Parameter::Immediate(rhs), Parameter::Relative(_)) => lhs.checked_add(rhs),
+        Instruction::Multiply(Parameter::Immediate(lhs), Parameter::Immediate(rhs), Parameter::Relative(_)) => lhs.checked_mul(rhs),
+        _ => None
+    };
+
+    value.and_then(|value| value.to_usize()) == Some(address)
+}
+
+#[test]
+fn test_cfg() {
+    use crate::assemble_with_labels;
+
+    let (program, labels) = assemble_with_labels("
+                IN -> [value]
+                ARB #1
+                ADD #back, #0 -> [rb+0]
+                JT #1, #double
+        back:   ARB #-1
+                JF [value], #zero
+                OUT [value]
+        zero:   HALT
+        double: MUL [value], #2 -> [value]
+                JF #0, [rb+0]
+        value:  .data 0
+    ").unwrap();
+
+    let cfg = Cfg::build(&program);
+    let start = |label: &str| cfg.block(labels[label]).unwrap();
+
+    assert_eq!(cfg.blocks().count(), 5);
+    assert_eq!(cfg.block(0).unwrap().exit, Exit::Call { target: labels["double"], return_to: labels["back"] });
+    assert_eq!(start("back").exit, Exit::Branch(Target::Address(labels["zero"]), labels["back"] + 5));
+    assert_eq!(cfg.block(labels["back"] + 5).unwrap().exit, Exit::Fallthrough(labels["zero"]));
+    assert_eq!(start("zero").exit, Exit::Halt);
+    assert_eq!(start("double").exit, Exit::Jump(Target::Return));
+    assert_eq!(cfg.subroutines(), BTreeSet::from([labels["double"]]));
+
+    let dot = cfg.to_dot();
+    assert!(dot.contains(&format!("b0 -> b{} [label=\"call\", style=bold];", labels["double"])));
+    assert!(dot.contains("(return)"));
+}
+
+#[test]
+fn test_indirect_jumps() {
+    use crate::assemble_with_labels;
+
+    let (program, labels) = assemble_with_labels("
+                IN -> [rb+1]
+                JT [rb+1], [rb+1]
+        below:  JT [rb+1], [rb-1]
+        above:  JT [rb+1], [rb+0]
+        done:   HALT
+    ").unwrap();
+
+    let cfg = Cfg::build(&program);
+    let start = |label: &str| cfg.block(labels[label]).unwrap();
+
+    assert_eq!(cfg.block(0).unwrap().exit, Exit::Branch(Target::Unknown, labels["below"]));
+    assert_eq!(start("below").exit, Exit::Branch(Target::Unknown, labels["above"]));
+    assert_eq!(start("above").exit, Exit::Branch(Target::Return, labels["done"]));
+}
+
+#[test]
+fn test_arcade_cfg() {
+    let program: IntcodeProgram = crate::intcode_parser(include_str!("../../input/2019/day13.txt")).unwrap();
+    let cfg = Cfg::build(&program);
+
+    assert!(cfg.subroutines().is_superset(&BTreeSet::from([393, 549, 578])));
+    assert_eq!(cfg.block(578).unwrap().exit, Exit::Jump(Target::Return));
+    assert!(cfg.blocks().all(|block| block.exit != Exit::Invalid));
+}
@@ -0,0 +1,148 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::disassembler::disassemble_where;
+use crate::{IntcodeProgram, Line, Machine, Memory, Word};
+
+// The set of addresses executed as instruction starts, merged across any
+// number of runs of the same program.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Coverage {
+    executed: BTreeSet<usize>
+}
+
+impl Coverage {
+    pub(crate) fn execute(&mut self, ip: usize) {
+        self.executed.insert(ip);
+    }
+
+    pub fn is_executed(&self, address: usize) -> bool {
+        self.executed.contains(&address)
+    }
+
+    pub fn executed(&self) -> impl Iterator<Item = usize> + '_ {
+        self.executed.iter().copied()
+    }
+
+    pub fn merge(&mut self, other: &Coverage) {
+        self.executed.extend(other.executed.iter().copied());
+    }
+
+    // Disassembles `program` starting instructions at every executed address,
+    // so words skipped over by real control flow are never mistaken for the
+    // operands of an unreached instruction.
+    fn lines<W: Word>(&self, program: &IntcodeProgram<W>) -> Vec<Line<W>> {
+        disassemble_where(program, |address, instruction| {
+            self.is_executed(address) || !(address+1..address+instruction.size()).any(|operand| self.is_executed(operand))
+        })
+    }
+
+    // Instruction starts in the disassembly of `program` that were never executed.
+    pub fn unreached<W: Word>(&self, program: &IntcodeProgram<W>) -> Vec<usize> {
+        self.lines(program).iter()
+            .filter_map(|line| match line {
+                Line::Instruction(address, _) if !self.is_executed(*address) => Some(*address),
+                _ => None
+            })
+            .collect()
+    }
+
+    // The disassembly of `program` with executed instructions marked `+` and
+    // never-reached ones marked `-`.
+    pub fn listing<W: Word>(&self, program: &IntcodeProgram<W>) -> String {
+        let mut listing = String::new();
+
+        for line in self.lines(program) {
+            let marker = match &line {
+                Line::Instruction(address, _) if self.is_executed(*address) => '+',
+                Line::Instruction(..) => '-',
+                Line::Data(..) => ' ',
+            };
+
+            writeln!(listing, "{} {}", marker, line).unwrap();
+        }
+
+        listing
+    }
+
+    pub fn to_json<W: Word>(&self, program: &IntcodeProgram<W>) -> String {
+        let executed = self.executed().collect::<Vec<_>>();
+        let unreached = self.unreached(program);
+        let array = |addresses: &[usize]| addresses.iter()
+            .map(|address| address.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!("{{\"instructions\":{},\"executed\":[{}],\"unreached\":[{}]}}",
+            executed.len() + unreached.len(), array(&executed), array(&unreached))
+    }
+}
+
+impl<W: Word, M: Memory<W>> Machine<W, M> {
+    pub fn start_coverage(&mut self) {
+        self.coverage = Some(Coverage::default());
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+}
+
+#[test]
+fn test_coverage() {
+    use crate::assemble;
+
+    let program = assemble("
+                IN -> [value]
+                JT [value], #nonzero
+                OUT #0
+                HALT
+        nonzero: OUT #1
+                HALT
+        value:  .data 0
+    ").unwrap();
+
+    let run = |input| {
+        let mut machine = Machine::new(&program);
+        machine.start_coverage();
+        machine.push_input(input);
+        while machine.run() != crate::Action::Halt {}
+        machine.take_coverage().unwrap()
+    };
+
+    let mut coverage = run(7);
+    assert_eq!(coverage.executed().collect::<Vec<_>>(), vec![0, 2, 8, 10]);
+    assert_eq!(coverage.unreached(&program), vec![5, 7]);
+    assert!(coverage.listing(&program).contains("- 0005: OUT #0"));
+    assert_eq!(coverage.to_json(&program), "{\"instructions\":6,\"executed\":[0,2,8,10],\"unreached\":[5,7]}");
+
+    coverage.merge(&run(0));
+    assert!(coverage.unreached(&program).is_empty());
+}
+
+#[test]
+fn test_diagnostic_coverage() {
+    let program = crate::intcode_parser(include_str!("../../input/2019/day5.txt")).unwrap();
+
+    let run = |input| {
+        let mut machine = Machine::new(&program);
+        machine.start_coverage();
+        machine.push_input(input);
+        while machine.run() != crate::Action::Halt {}
+        machine.take_coverage().unwrap()
+    };
+
+    let air_conditioner = run(1);
+    let thermal_radiator = run(5);
+
+    let mut merged = air_conditioner.clone();
+    merged.merge(&thermal_radiator);
+
+    assert!(merged.executed().count() > air_conditioner.executed().count());
+    assert!(merged.executed().count() > thermal_radiator.executed().count());
+    assert!(merged.unreached(&program).len() < air_conditioner.unreached(&program).len());
+}
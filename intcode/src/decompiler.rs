@@ -0,0 +1,567 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fmt::{self, Write};
+
+use crate::cfg::stores_return_address;
+use crate::{Block, Cfg, Exit, Instruction, IntcodeProgram, Parameter, Target, Word};
+
+const INDENT: &str = "    ";
+
+// A jump condition: true when `expr` is non-zero, or zero if negated.
+#[derive(Clone, Debug)]
+struct Condition {
+    expr: String,
+    negated: bool
+}
+
+impl Condition {
+    fn not(self) -> Condition {
+        Condition { negated: !self.negated, ..self }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.negated, self.expr.contains(' ')) {
+            (false, _) => write!(f, "{}", self.expr),
+            (true, false) => write!(f, "!{}", self.expr),
+            (true, true) => write!(f, "!({})", self.expr),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Statement {
+    Line(String),
+    Label(usize),
+    If(Condition, Vec<Statement>, Vec<Statement>),
+    Loop(Vec<Statement>),
+    While(Condition, Vec<Statement>),
+    DoWhile(Vec<Statement>, Condition),
+    Break,
+    Continue,
+    Goto(usize)
+}
+
+// The loop a region is nested in, for turning jumps into `break` and `continue`.
+#[derive(Clone, Copy)]
+struct Enclosing {
+    header: usize,
+    end: usize
+}
+
+// The blocks reachable from a call target without entering other calls,
+// with the relative base offset from the entry at the start of each block.
+struct Function<'a, W> {
+    entry: usize,
+    blocks: BTreeMap<usize, &'a Block<W>>,
+    offsets: HashMap<usize, Option<isize>>,
+    frame_size: isize,
+    params: usize
+}
+
+impl<'a, W: Word> Function<'a, W> {
+    fn new(cfg: &'a Cfg<W>, entry: usize) -> Self {
+        let mut blocks = BTreeMap::new();
+        let mut offsets = HashMap::new();
+        let mut pending = VecDeque::from([(entry, Some(0))]);
+
+        while let Some((start, offset)) = pending.pop_front() {
+            let block = match cfg.block(start) {
+                Some(block) if !blocks.contains_key(&start) => block,
+                _ => continue
+            };
+
+            blocks.insert(start, block);
+            offsets.insert(start, offset);
+
+            let offset = block.instructions.iter().fold(offset, |offset, (_, instruction)| adjust(offset, instruction));
+            let successors = match block.exit {
+                Exit::Call { return_to, .. } => vec![return_to],
+                exit => exit.successors()
+            };
+
+            for successor in successors {
+                pending.push_back((successor, offset));
+            }
+        }
+
+        // The first stack frame allocation sets the frame size; anything
+        // addressed above it is an argument slot for a call being set up.
+        let frame_size = blocks.values()
+            .flat_map(|block| block.instructions.iter())
+            .find_map(|(_, instruction)| match instruction {
+                Instruction::AdjustRelativeBase(Parameter::Immediate(size)) => size.to_isize().filter(|size| *size > 0),
+                _ => None
+            })
+            .unwrap_or(0);
+
+        Function { entry, blocks, offsets, frame_size, params: 0 }
+    }
+
+    fn name(entry: usize) -> String {
+        if entry == 0 { "main".to_string() } else { format!("f{:04}", entry) }
+    }
+
+    // The name of the relative base slot `slot` words above the base on entry.
+    fn slot(&self, slot: isize) -> String {
+        match slot {
+            0 => "ret".to_string(),
+            slot if slot < 0 => format!("frame[{}]", slot),
+            slot if slot <= self.params as isize && slot <= self.frame_size => format!("arg{}", slot),
+            slot if slot <= self.frame_size => format!("local{}", slot),
+            slot => format!("out{}", slot - self.frame_size),
+        }
+    }
+
+    // A branch into the middle of an earlier instruction can leave `address`
+    // past the end of the region, which then has nothing left in it.
+    fn block_at(&self, address: usize, end: usize) -> Option<&'a Block<W>> {
+        if address >= end {
+            return None;
+        }
+
+        self.blocks.range(address..end).next().map(|(_, block)| *block)
+    }
+}
+
+fn adjust<W: Word>(offset: Option<isize>, instruction: &Instruction<W>) -> Option<isize> {
+    match instruction {
+        Instruction::AdjustRelativeBase(Parameter::Immediate(value)) => offset?.checked_add(value.to_isize()?),
+        Instruction::AdjustRelativeBase(_) => None,
+        _ => offset
+    }
+}
+
+struct Decompiler<'a, W> {
+    functions: BTreeMap<usize, Function<'a, W>>,
+    // Operand words that the program overwrites, i.e. self-modifying code.
+    patched: BTreeSet<usize>
+}
+
+impl<'a, W: Word> Decompiler<'a, W> {
+    fn new(cfg: &'a Cfg<W>) -> Self {
+        let mut functions = BTreeMap::new();
+        for entry in std::iter::once(0).chain(cfg.subroutines()) {
+            functions.insert(entry, Function::new(cfg, entry));
+        }
+
+        // A function takes as many arguments as the highest slot any caller fills in.
+        let mut params = BTreeMap::new();
+        for function in functions.values() {
+            for block in function.blocks.values() {
+                let target = match block.exit {
+                    Exit::Call { target, .. } => target,
+                    _ => continue
+                };
+
+                let mut offset = function.offsets[&block.start];
+                for (_, instruction) in &block.instructions {
+                    if let (Some(offset), Some(Parameter::Relative(slot))) = (offset, instruction.output()) {
+                        if let Some(argument) = slot.to_isize().and_then(|slot| offset.checked_add(slot)?.checked_sub(function.frame_size)) {
+                            let count = params.entry(target).or_insert(0);
+                            *count = (*count).max(argument.max(0) as usize);
+                        }
+                    }
+
+                    offset = adjust(offset, instruction);
+                }
+            }
+        }
+
+        for (entry, count) in params {
+            if let Some(function) = functions.get_mut(&entry) {
+                function.params = count;
+            }
+        }
+
+        let spans = cfg.blocks()
+            .flat_map(|block| block.instructions.iter())
+            .map(|(address, instruction)| (address + 1, address + instruction.size()))
+            .collect::<Vec<_>>();
+
+        let patched = cfg.blocks()
+            .flat_map(|block| block.instructions.iter())
+            .filter_map(|(_, instruction)| match instruction.output() {
+                Some(Parameter::Position(address)) => Some(*address),
+                _ => None
+            })
+            .filter(|address| spans.iter().any(|(start, end)| (start..end).contains(&address)))
+            .collect();
+
+        Decompiler { functions, patched }
+    }
+
+    fn operand(&self, function: &Function<W>, offset: Option<isize>, word: usize, parameter: &Parameter<W>) -> String {
+        if self.patched.contains(&word) {
+            return match parameter {
+                Parameter::Position(_) => format!("mem[v{}]", word),
+                Parameter::Immediate(_) => format!("v{}", word),
+                Parameter::Relative(_) => format!("frame[v{}]", word),
+            };
+        }
+
+        match parameter {
+            Parameter::Position(address) => format!("v{}", address),
+            Parameter::Immediate(value) => value.to_string(),
+            Parameter::Relative(slot) => match offset.zip(slot.to_isize()).and_then(|(offset, slot)| offset.checked_add(slot)) {
+                Some(slot) => function.slot(slot),
+                None => format!("rb[{}]", slot)
+            }
+        }
+    }
+
+    // The statements for the instructions of a block, leaving out the
+    // terminating jump and the stack frame bookkeeping, along with the
+    // condition and target operand of that jump.
+    fn body(&self, function: &Function<W>, block: &Block<W>) -> (Vec<Statement>, Option<(Condition, String)>) {
+        let mut statements = Vec::new();
+        let mut jump = None;
+        let mut offset = function.offsets[&block.start];
+
+        for (address, instruction) in &block.instructions {
+            let operands = instruction.parameters().iter().enumerate()
+                .map(|(index, parameter)| self.operand(function, offset, address + 1 + index, parameter))
+                .collect::<Vec<_>>();
+
+            let line = match instruction {
+                Instruction::Add(..) => match (operands[0].as_str(), operands[1].as_str()) {
+                    ("0", value) | (value, "0") => Some(format!("{} = {}", operands[2], value)),
+                    (lhs, rhs) => match rhs.strip_prefix('-') {
+                        Some(rhs) => Some(format!("{} = {} - {}", operands[2], lhs, rhs)),
+                        None => Some(format!("{} = {} + {}", operands[2], lhs, rhs))
+                    }
+                },
+
+                Instruction::Multiply(..) => match (operands[0].as_str(), operands[1].as_str()) {
+                    ("1", value) | (value, "1") => Some(format!("{} = {}", operands[2], value)),
+                    (lhs, rhs) => Some(format!("{} = {} * {}", operands[2], lhs, rhs))
+                },
+
+                Instruction::LessThan(..) => Some(format!("{} = {} < {}", operands[2], operands[0], operands[1])),
+                Instruction::Equals(..) => Some(format!("{} = {} == {}", operands[2], operands[0], operands[1])),
+                Instruction::Input(..) => Some(format!("{} = input()", operands[0])),
+                Instruction::Output(..) => Some(format!("output({})", operands[0])),
+                Instruction::Halt => Some("halt".to_string()),
+
+                Instruction::JumpIfTrue(..) | Instruction::JumpIfFalse(..) => {
+                    let negated = matches!(instruction, Instruction::JumpIfFalse(..));
+                    jump = Some((Condition { expr: operands[0].clone(), negated }, operands[1].clone()));
+
+                    match block.exit {
+                        Exit::Jump(Target::Unknown) => Some(format!("goto *{}", operands[1])),
+                        _ => None
+                    }
+                },
+
+                Instruction::AdjustRelativeBase(_) if adjust(offset, instruction).is_some() => None,
+                Instruction::AdjustRelativeBase(_) => Some(format!("rb += {}", operands[0])),
+            };
+
+            let return_address = match block.exit {
+                Exit::Call { return_to, .. } => stores_return_address(instruction, return_to),
+                _ => false
+            };
+
+            if let Some(line) = line.filter(|_| !return_address) {
+                statements.push(Statement::Line(line));
+            }
+
+            offset = adjust(offset, instruction);
+        }
+
+        (statements, jump)
+    }
+
+    fn jump(&self, target: usize, enclosing: Option<Enclosing>, follow: Option<usize>) -> Option<Statement> {
+        match enclosing {
+            _ if Some(target) == follow => None,
+            Some(enclosing) if enclosing.header == target => Some(Statement::Continue),
+            Some(enclosing) if enclosing.end == target => Some(Statement::Break),
+            _ => Some(Statement::Goto(target))
+        }
+    }
+
+    // Structures the blocks of `function` in `start..end`. Jumping to `follow`
+    // means carrying on after the region; `header` is a loop header that
+    // has already been turned into a loop.
+    fn structure(&self, function: &Function<W>, start: usize, end: usize, enclosing: Option<Enclosing>, follow: Option<usize>, header: Option<usize>) -> Vec<Statement> {
+        let mut statements = Vec::new();
+        let mut address = start;
+        let reaches = |target: usize| target < end || (target == end && follow == Some(end));
+
+        while let Some(block) = function.block_at(address, end) {
+            if Some(block.start) != header {
+                let back_edge = function.blocks.range(block.start..end)
+                    .filter(|(_, source)| match source.exit {
+                        Exit::Jump(Target::Address(target)) | Exit::Branch(Target::Address(target), _) => target == block.start,
+                        _ => false
+                    })
+                    .map(|(_, source)| source.end())
+                    .max();
+
+                if let Some(loop_end) = back_edge {
+                    statements.push(Statement::Label(block.start));
+                    statements.push(self.structure_loop(function, block.start, loop_end));
+                    address = loop_end;
+                    continue;
+                }
+
+                statements.push(Statement::Label(block.start));
+            }
+
+            let (body, jump) = self.body(function, block);
+            statements.extend(body);
+            address = block.end().max(block.start + 1);
+
+            match block.exit {
+                Exit::Fallthrough(next) => address = next,
+
+                Exit::Jump(Target::Address(target)) => statements.extend(self.jump(target, enclosing, follow)),
+                Exit::Jump(Target::Return) => statements.push(Statement::Line("return".to_string())),
+                Exit::Jump(Target::Unknown) | Exit::Halt => {},
+                Exit::Invalid => statements.push(Statement::Line("invalid".to_string())),
+
+                Exit::Call { target, return_to } => {
+                    let callee = &self.functions[&target];
+                    let arguments = (1..=callee.params).map(|index| format!("out{}", index)).collect::<Vec<_>>();
+
+                    statements.push(Statement::Line(format!("{}({})", Function::<W>::name(target), arguments.join(", "))));
+                    address = return_to;
+                },
+
+                Exit::Branch(target, next) => {
+                    let (taken, operand) = jump.expect("branch without a jump instruction");
+                    address = next;
+
+                    match target {
+                        Target::Address(target) if next < target && reaches(target) => {
+                            let otherwise = function.blocks.range(next..target).next_back()
+                                .and_then(|(_, last)| match last.exit {
+                                    Exit::Jump(Target::Address(join)) if join > target && reaches(join) => Some(join),
+                                    _ => None
+                                });
+
+                            match otherwise {
+                                Some(join) => {
+                                    let then = self.structure(function, next, target, enclosing, Some(join), None);
+                                    let other = self.structure(function, target, join, enclosing, Some(join), None);
+                                    statements.push(Statement::If(taken.not(), then, other));
+                                    address = join;
+                                },
+
+                                None => {
+                                    let then = self.structure(function, next, target, enclosing, Some(target), None);
+                                    statements.push(Statement::If(taken.not(), then, Vec::new()));
+                                    address = target;
+                                }
+                            }
+                        },
+
+                        Target::Address(target) => {
+                            if let Some(jump) = self.jump(target, enclosing, follow) {
+                                statements.push(Statement::If(taken, vec![jump], Vec::new()));
+                            }
+                        },
+
+                        Target::Return => statements.push(Statement::If(taken, vec![Statement::Line("return".to_string())], Vec::new())),
+                        Target::Unknown => statements.push(Statement::If(taken, vec![Statement::Line(format!("goto *{}", operand))], Vec::new())),
+                    }
+                }
+            }
+        }
+
+        statements
+    }
+
+    fn structure_loop(&self, function: &Function<W>, header: usize, end: usize) -> Statement {
+        let enclosing = Some(Enclosing { header, end });
+        let mut body = self.structure(function, header, end, enclosing, None, Some(header));
+
+        // Running off the end of the last block leaves the loop.
+        match body.last() {
+            Some(Statement::Continue) => { body.pop(); },
+            _ => body.push(Statement::Break)
+        }
+
+        if let [.., Statement::If(_, then, other), Statement::Break] = &body[..] {
+            if matches!(then[..], [Statement::Continue]) && other.is_empty() {
+                body.pop();
+                if let Some(Statement::If(condition, _, _)) = body.pop() {
+                    return Statement::DoWhile(body, condition);
+                }
+            }
+        }
+
+        if let Some(Statement::If(_, then, other)) = body.first() {
+            if matches!(then[..], [Statement::Break]) && other.is_empty() {
+                if let Statement::If(condition, _, _) = body.remove(0) {
+                    return Statement::While(condition.not(), body);
+                }
+            }
+        }
+
+        Statement::Loop(body)
+    }
+
+    fn decompile(&self) -> String {
+        let mut output = String::new();
+
+        for function in self.functions.values() {
+            let params = (1..=function.params).map(|index| format!("arg{}", index)).collect::<Vec<_>>();
+            let end = function.blocks.values().map(|block| block.end()).max().unwrap_or(function.entry) + 1;
+            let start = function.blocks.keys().next().copied().unwrap_or(function.entry);
+            let statements = self.structure(function, start, end, None, None, None);
+
+            let mut gotos = BTreeSet::new();
+            collect_gotos(&statements, &mut gotos);
+
+            if !output.is_empty() {
+                output.push('\n');
+            }
+
+            writeln!(output, "fn {}({}) {{", Function::<W>::name(function.entry), params.join(", ")).unwrap();
+            write_statements(&mut output, &statements, &gotos, 1);
+            writeln!(output, "}}").unwrap();
+        }
+
+        output
+    }
+}
+
+fn collect_gotos(statements: &[Statement], gotos: &mut BTreeSet<usize>) {
+    for statement in statements {
+        match statement {
+            Statement::Goto(target) => { gotos.insert(*target); },
+            Statement::If(_, then, other) => {
+                collect_gotos(then, gotos);
+                collect_gotos(other, gotos);
+            },
+            Statement::Loop(body) | Statement::While(_, body) | Statement::DoWhile(body, _) => collect_gotos(body, gotos),
+            _ => {}
+        }
+    }
+}
+
+fn write_statements(output: &mut String, statements: &[Statement], gotos: &BTreeSet<usize>, depth: usize) {
+    let indent = INDENT.repeat(depth);
+
+    for statement in statements {
+        match statement {
+            Statement::Line(line) => writeln!(output, "{}{}", indent, line).unwrap(),
+            Statement::Label(address) if gotos.contains(address) => writeln!(output, "{}L{:04}:", INDENT.repeat(depth - 1), address).unwrap(),
+            Statement::Label(_) => {},
+            Statement::Break => writeln!(output, "{}break", indent).unwrap(),
+            Statement::Continue => writeln!(output, "{}continue", indent).unwrap(),
+            Statement::Goto(target) => writeln!(output, "{}goto L{:04}", indent, target).unwrap(),
+
+            Statement::If(condition, then, other) => {
+                writeln!(output, "{}if {} {{", indent, condition).unwrap();
+                write_statements(output, then, gotos, depth + 1);
+
+                if !other.is_empty() {
+                    writeln!(output, "{}}} else {{", indent).unwrap();
+                    write_statements(output, other, gotos, depth + 1);
+                }
+
+                writeln!(output, "{}}}", indent).unwrap();
+            },
+
+            Statement::Loop(body) => {
+                writeln!(output, "{}loop {{", indent).unwrap();
+                write_statements(output, body, gotos, depth + 1);
+                writeln!(output, "{}}}", indent).unwrap();
+            },
+
+            Statement::While(condition, body) => {
+                writeln!(output, "{}while {} {{", indent, condition).unwrap();
+                write_statements(output, body, gotos, depth + 1);
+                writeln!(output, "{}}}", indent).unwrap();
+            },
+
+            Statement::DoWhile(body, condition) => {
+                writeln!(output, "{}do {{", indent).unwrap();
+                write_statements(output, body, gotos, depth + 1);
+                writeln!(output, "{}}} while {}", indent, condition).unwrap();
+            },
+        }
+    }
+}
+
+// Pseudocode for `program` with one function per subroutine found in its
+// control-flow graph. Memory cells become `v<address>` variables and stack
+// frame slots `arg`, `local` or `out` (arguments of the next call) names.
+pub fn decompile<W: Word>(program: &IntcodeProgram<W>) -> String {
+    let cfg = Cfg::build(program);
+    Decompiler::new(&cfg).decompile()
+}
+
+#[test]
+fn test_decompile() {
+    let program = crate::assemble("
+                ARB #100
+                IN -> [n]
+        loop:   JF [n], #done
+                ADD [n], #0 -> [rb+1]
+                ADD #back, #0 -> [rb+0]
+                JT #1, #square
+        back:   OUT [rb+1]
+                ADD [n], #-1 -> [n]
+                JT #1, #loop
+        done:   HALT
+        square: ARB #2
+                MUL [rb-1], [rb-1] -> [rb-1]
+                LT [rb-1], #10 -> [rb+0]
+                JF [rb+0], #large
+                ADD [small], #1 -> [small]
+        large:  ARB #-2
+                JT #1, [rb+0]
+        n:      .data 0
+        small:  .data 0
+    ").unwrap();
+
+    assert_eq!(decompile(&program), "\
+fn main() {
+    v50 = input()
+    while v50 {
+        out1 = v50
+        f0028(out1)
+        output(out1)
+        v50 = v50 - 1
+    }
+    halt
+}
+
+fn f0028(arg1) {
+    arg1 = arg1 * arg1
+    local2 = arg1 < 10
+    if local2 {
+        v51 = v51 + 1
+    }
+    return
+}
+");
+}
+
+#[test]
+fn test_decompile_arcade() {
+    let program: IntcodeProgram = crate::intcode_parser(include_str!("../../input/2019/day13.txt")).unwrap();
+    let pseudocode = decompile(&program);
+
+    assert!(pseudocode.contains("fn f0549(arg1, arg2, arg3) {"));
+    assert!(pseudocode.contains("    output(arg1)\n    output(arg2)\n    output(arg3)\n"));
+    assert!(pseudocode.contains("mem[v566] = arg3"));
+}
+
+#[test]
+fn test_decompile_overlapping_branches() {
+    // Branches land in the middle of earlier instructions, so regions
+    // being structured can end before they start.
+    let program: IntcodeProgram<i64> = crate::intcode_parser("244627464324883209,35,203,35,1206,5,19,7,7,2106,204,21101,-7,22,37,4,12,2101,2105,-253490858036595468,8,14,-39,11,1106,6023075750150402074,32,1205").unwrap();
+    assert!(decompile(&program).starts_with("fn main() {"));
+}
+
+#[test]
+fn test_decompile_overflowing_offsets() {
+    let program: IntcodeProgram<i64> = crate::intcode_parser("109,9223372036854775806,109,9223372036854775807,99").unwrap();
+    assert!(decompile(&program).starts_with("fn main() {"));
+}
@@ -0,0 +1,97 @@
+use std::fmt;
+
+use crate::{DenseMemory, Instruction, IntcodeProgram, Memory, Word};
+
+const DATA_PER_LINE: usize = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Line<W = isize> {
+    Instruction(usize, Instruction<W>),
+    Data(usize, Vec<W>)
+}
+
+impl<W: Word> Line<W> {
+    pub fn address(&self) -> usize {
+        match self {
+            Line::Instruction(address, _) => *address,
+            Line::Data(address, _) => *address,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            Line::Instruction(_, instruction) => instruction.size(),
+            Line::Data(_, values) => values.len(),
+        }
+    }
+}
+
+impl<W: Word> fmt::Display for Line<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Line::Instruction(address, instruction) => write!(f, "{:04}: {}", address, instruction),
+
+            Line::Data(address, values) => {
+                let values = values.iter()
+                    .map(|value| value.to_string())
+                    .collect::<Vec<_>>();
+
+                write!(f, "{:04}: .data {}", address, values.join(", "))
+            }
+        }
+    }
+}
+
+pub fn disassemble<W: Word>(program: &IntcodeProgram<W>) -> Vec<Line<W>> {
+    disassemble_where(program, |_, _| true)
+}
+
+// A linear sweep that only decodes an instruction where `accept` allows it,
+// falling back to data otherwise.
+pub(crate) fn disassemble_where<W: Word, F: Fn(usize, &Instruction<W>) -> bool>(program: &IntcodeProgram<W>, accept: F) -> Vec<Line<W>> {
+    let memory = DenseMemory::from_program(program);
+    let mut lines = Vec::new();
+    let mut address = 0;
+
+    while address < program.len() {
+        match Instruction::decode(&memory, address) {
+            Ok(instruction) if address + instruction.size() <= program.len() && accept(address, &instruction) => {
+                let size = instruction.size();
+                lines.push(Line::Instruction(address, instruction));
+                address += size;
+            },
+
+            _ => {
+                match lines.last_mut() {
+                    Some(Line::Data(_, values)) if values.len() < DATA_PER_LINE => values.push(program[address].clone()),
+                    _ => lines.push(Line::Data(address, vec![program[address].clone()]))
+                }
+
+                address += 1;
+            }
+        }
+    }
+
+    lines
+}
+
+pub fn listing<W: Word>(program: &IntcodeProgram<W>) -> String {
+    disassemble(program).iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+#[test]
+fn test_listing() {
+    let program: IntcodeProgram = crate::intcode_parser("3,15,1101,-3,5,100,1005,15,12,204,-1,99,0,0,0,0").unwrap();
+
+    assert_eq!(listing(&program), [
+        "0000: IN -> [15]",
+        "0002: ADD #-3, #5 -> [100]",
+        "0006: JT [15], #12",
+        "0009: OUT [rb-1]",
+        "0011: HALT",
+        "0012: .data 0, 0, 0, 0",
+        ""
+    ].join("\n"));
+}
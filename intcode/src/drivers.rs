@@ -0,0 +1,151 @@
+use std::iter;
+
+use crate::{Action, InputSource, IterSource, IntcodeError, Machine, Memory, Word};
+
+// Runs a machine one output at a time. Input the program asks for is taken
+// from the source, and the iterator ends for good once the program halts,
+// needs input the source cannot provide or runs out of budget. A machine
+// that has already halted produces nothing. The iterator panics on a fault;
+// `try_next` returns it instead.
+pub struct Outputs<'a, W: Word, M: Memory<W>, I> {
+    machine: &'a mut Machine<W, M>,
+    source: I,
+    stopped: Option<Action<W>>
+}
+
+impl<W: Word, M: Memory<W>, I> Outputs<'_, W, M, I> {
+    // Why the iterator ended, or None while it is still producing outputs.
+    pub fn stopped(&self) -> Option<&Action<W>> {
+        self.stopped.as_ref()
+    }
+}
+
+impl<W: Word, M: Memory<W>, I: InputSource<W>> Outputs<'_, W, M, I> {
+    pub fn try_next(&mut self) -> Result<Option<W>, IntcodeError<W>> {
+        while self.stopped.is_none() {
+            match self.machine.try_run()? {
+                Action::Output(value) => return Ok(Some(value)),
+
+                Action::RequiresInput => match self.source.next_input() {
+                    Some(value) => self.machine.push_input(value),
+                    None => self.stopped = Some(Action::RequiresInput)
+                },
+
+                action => self.stopped = Some(action)
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl<W: Word, M: Memory<W>, I: InputSource<W>> Iterator for Outputs<'_, W, M, I> {
+    type Item = W;
+
+    fn next(&mut self) -> Option<W> {
+        self.try_next().unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+impl<W: Word, M: Memory<W>> Machine<W, M> {
+    // Outputs until the program halts or needs more input than was pushed.
+    pub fn outputs(&mut self) -> Outputs<'_, W, M, IterSource<iter::Empty<W>>> {
+        self.outputs_with(IterSource::new(iter::empty()))
+    }
+
+    // Outputs, asking `source` for input only when the program wants it.
+    // Closures returning `Option<W>` work as a source.
+    pub fn outputs_with<I: InputSource<W>>(&mut self, source: I) -> Outputs<'_, W, M, I> {
+        let stopped = self.halted().then_some(Action::Halt);
+        Outputs { machine: self, source, stopped }
+    }
+
+    // The next output, or None if the program stops first.
+    pub fn run_until_output(&mut self) -> Option<W> {
+        self.try_run_until_output().unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_run_until_output(&mut self) -> Result<Option<W>, IntcodeError<W>> {
+        self.outputs().try_next()
+    }
+
+    // Every output up to the program halting. Panics if it stops for input
+    // or runs out of budget instead.
+    pub fn run_to_halt(&mut self) -> Vec<W> {
+        self.try_run_to_halt().unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    // As `run_to_halt`, but returns a fault rather than panicking on it.
+    pub fn try_run_to_halt(&mut self) -> Result<Vec<W>, IntcodeError<W>> {
+        let mut outputs = self.outputs();
+        let mut values = Vec::new();
+
+        while let Some(value) = outputs.try_next()? {
+            values.push(value);
+        }
+
+        match outputs.stopped() {
+            Some(Action::Halt) => Ok(values),
+            action => panic!("Machine stopped before halting: {:?}", action)
+        }
+    }
+}
+
+#[test]
+fn test_outputs() {
+    use crate::{intcode_parser, IntcodeProgram};
+
+    // Outputs 1 if its input is 8, otherwise 0.
+    let equals_eight: IntcodeProgram = intcode_parser("3,9,8,9,10,9,4,9,99,-1,8").unwrap();
+
+    let mut machine = Machine::new(&equals_eight);
+    machine.push_input(8);
+    assert_eq!(machine.run_to_halt(), vec![1]);
+
+    let mut machine = Machine::new(&equals_eight);
+    assert_eq!(machine.run_until_output(), None);
+    machine.push_input(7);
+    assert_eq!(machine.run_until_output(), Some(0));
+
+    // Outputs the sum of each pair of inputs, forever.
+    let program: IntcodeProgram = intcode_parser("3,13,3,14,1,13,14,15,4,15,1105,1,0,0,0,0").unwrap();
+
+    let mut machine = Machine::new(&program);
+    let mut next = 0;
+    let mut outputs = machine.outputs_with(|| {
+        next += 1;
+        Some(next)
+    });
+    assert_eq!(outputs.by_ref().take(3).collect::<Vec<_>>(), vec![3, 7, 11]);
+    assert_eq!(outputs.stopped(), None);
+
+    let mut outputs = machine.outputs_with(IterSource::new(vec![10, 20, 30]));
+    assert_eq!(outputs.by_ref().collect::<Vec<_>>(), vec![30]);
+    assert_eq!(outputs.stopped(), Some(&Action::RequiresInput));
+}
+
+#[test]
+fn test_outputs_after_halt_and_fault() {
+    use crate::{intcode_parser, IntcodeProgram};
+
+    // Outputs 5 and halts, with more outputs after the halt.
+    let program: IntcodeProgram = intcode_parser("104,5,99,104,6,99").unwrap();
+
+    let mut machine = Machine::new(&program);
+    assert_eq!(machine.run_until_output(), Some(5));
+    assert_eq!(machine.run_until_output(), None);
+    assert!(machine.halted());
+    assert_eq!(machine.run_until_output(), None);
+    assert_eq!(machine.run_to_halt(), vec![]);
+
+    // Outputs 5, then jumps out of the program.
+    let program: IntcodeProgram = intcode_parser("104,5,1105,1,-1").unwrap();
+
+    let mut machine = Machine::new(&program);
+    assert_eq!(machine.try_run_until_output(), Ok(Some(5)));
+    assert!(matches!(machine.try_run_until_output(), Err(IntcodeError::JumpOutOfRange { ip: 2, .. })));
+    assert!(!machine.halted());
+
+    let mut machine = Machine::new(&program);
+    assert!(machine.try_run_to_halt().is_err());
+}
@@ -0,0 +1,65 @@
+use std::io::{self, Read, Write};
+
+pub(crate) fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+pub(crate) fn zigzag(value: i128) -> u128 {
+    ((value << 1) ^ (value >> 127)) as u128
+}
+
+pub(crate) fn unzigzag(value: u128) -> i128 {
+    ((value >> 1) as i128) ^ -((value & 1) as i128)
+}
+
+pub(crate) fn write_varint<W: Write>(writer: &mut W, mut value: u128) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+pub(crate) fn read_varint<R: Read>(reader: &mut R) -> io::Result<u128> {
+    let mut value = 0u128;
+    let mut shift = 0;
+
+    loop {
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+
+        if shift >= 128 {
+            return Err(invalid_data("varint is too long"));
+        }
+
+        value |= ((byte[0] & 0x7f) as u128) << shift;
+
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+
+        shift += 7;
+    }
+}
+
+#[test]
+fn test_varint_round_trip() {
+    let values = [0, 1, -1, 63, -64, 64, 300, -300, i128::MAX, i128::MIN];
+
+    let mut encoded = Vec::new();
+    for value in values.iter() {
+        write_varint(&mut encoded, zigzag(*value)).unwrap();
+    }
+
+    let mut reader = &encoded[..];
+    for value in values.iter() {
+        assert_eq!(unzigzag(read_varint(&mut reader).unwrap()), *value);
+    }
+
+    assert_eq!(encoded[..4], [0, 2, 1, 126]);
+}
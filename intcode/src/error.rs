@@ -0,0 +1,71 @@
+use std::fmt;
+
+use crate::Word;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum IntcodeError<W = isize> {
+    InvalidOpcode { ip: usize, instruction: W },
+    InvalidMode { ip: usize, instruction: W, mode: isize },
+    WriteToImmediate { ip: usize, instruction: W },
+    NegativeAddress { ip: usize, instruction: W, address: W },
+    AddressOutOfRange { ip: usize, instruction: W, address: W },
+    JumpOutOfRange { ip: usize, instruction: W, target: W },
+    Overflow { ip: usize, instruction: W, lhs: W, rhs: W },
+}
+
+impl<W: Word> IntcodeError<W> {
+    pub fn ip(&self) -> usize {
+        match self {
+            IntcodeError::InvalidOpcode { ip, .. } => *ip,
+            IntcodeError::InvalidMode { ip, .. } => *ip,
+            IntcodeError::WriteToImmediate { ip, .. } => *ip,
+            IntcodeError::NegativeAddress { ip, .. } => *ip,
+            IntcodeError::AddressOutOfRange { ip, .. } => *ip,
+            IntcodeError::JumpOutOfRange { ip, .. } => *ip,
+            IntcodeError::Overflow { ip, .. } => *ip,
+        }
+    }
+
+    pub fn instruction(&self) -> W {
+        match self {
+            IntcodeError::InvalidOpcode { instruction, .. } => instruction.clone(),
+            IntcodeError::InvalidMode { instruction, .. } => instruction.clone(),
+            IntcodeError::WriteToImmediate { instruction, .. } => instruction.clone(),
+            IntcodeError::NegativeAddress { instruction, .. } => instruction.clone(),
+            IntcodeError::AddressOutOfRange { instruction, .. } => instruction.clone(),
+            IntcodeError::JumpOutOfRange { instruction, .. } => instruction.clone(),
+            IntcodeError::Overflow { instruction, .. } => instruction.clone(),
+        }
+    }
+}
+
+impl<W: Word> fmt::Display for IntcodeError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntcodeError::InvalidOpcode { ip, instruction } => match instruction.to_isize() {
+                Some(instruction) => write!(f, "invalid opcode {} at IP {}", instruction % 100, ip),
+                None => write!(f, "invalid instruction {} at IP {}", instruction, ip)
+            },
+
+            IntcodeError::InvalidMode { ip, instruction, mode } =>
+                write!(f, "invalid parameter mode {} in instruction {} at IP {}", mode, instruction, ip),
+
+            IntcodeError::WriteToImmediate { ip, instruction } =>
+                write!(f, "write to immediate-mode parameter in instruction {} at IP {}", instruction, ip),
+
+            IntcodeError::NegativeAddress { ip, instruction, address } =>
+                write!(f, "negative address {} in instruction {} at IP {}", address, instruction, ip),
+
+            IntcodeError::AddressOutOfRange { ip, instruction, address } =>
+                write!(f, "address {} is out of range in instruction {} at IP {}", address, instruction, ip),
+
+            IntcodeError::JumpOutOfRange { ip, instruction, target } =>
+                write!(f, "jump to out-of-range address {} in instruction {} at IP {}", target, instruction, ip),
+
+            IntcodeError::Overflow { ip, instruction, lhs, rhs } =>
+                write!(f, "arithmetic overflow on {} and {} in instruction {} at IP {}", lhs, rhs, instruction, ip),
+        }
+    }
+}
+
+impl<W: Word> std::error::Error for IntcodeError<W> {}
@@ -0,0 +1,206 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use futures_core::Stream;
+
+struct Shared<T> {
+    queue: VecDeque<T>,
+    waker: Option<Waker>,
+    senders: usize
+}
+
+// A single-threaded unbounded channel. The receiver is a stream that ends
+// once every sender has been dropped and the queue is empty.
+pub struct Sender<T>(Rc<RefCell<Shared<T>>>);
+
+pub struct Receiver<T>(Rc<RefCell<Shared<T>>>);
+
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let shared = Rc::new(RefCell::new(Shared { queue: VecDeque::new(), waker: None, senders: 1 }));
+    (Sender(shared.clone()), Receiver(shared))
+}
+
+impl<T> Sender<T> {
+    pub fn send(&self, value: T) {
+        let mut shared = self.0.borrow_mut();
+        shared.queue.push_back(value);
+
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.0.borrow_mut().senders += 1;
+        Sender(self.0.clone())
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut shared = self.0.borrow_mut();
+        shared.senders -= 1;
+
+        if shared.senders == 0 {
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().queue.is_empty()
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<T>> {
+        let mut shared = self.0.borrow_mut();
+
+        if let Some(value) = shared.queue.pop_front() {
+            return Poll::Ready(Some(value));
+        }
+
+        if shared.senders == 0 {
+            return Poll::Ready(None);
+        }
+
+        shared.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+pub struct Next<'a, S> {
+    stream: &'a mut S
+}
+
+// Resolves to the next item of a stream, or None once it has ended.
+pub fn next<S: Stream + Unpin>(stream: &mut S) -> Next<'_, S> {
+    Next { stream }
+}
+
+impl<S: Stream + Unpin> Future for Next<'_, S> {
+    type Output = Option<S::Item>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        Pin::new(&mut *self.stream).poll_next(cx)
+    }
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+// Runs a future to completion on the current thread, parking it whenever
+// the future is waiting to be woken.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+
+        thread::park();
+    }
+}
+
+struct TaskWaker {
+    task: usize,
+    ready: Arc<Mutex<Vec<usize>>>
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.ready.lock().unwrap().push(self.task);
+    }
+}
+
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+// A minimal local executor. Tasks are only polled after being woken, so
+// `run_until_stalled` returning means every remaining task is waiting on
+// something only the caller can provide.
+#[derive(Default)]
+pub struct Executor {
+    tasks: Vec<Option<Task>>,
+    ready: Arc<Mutex<Vec<usize>>>
+}
+
+impl Executor {
+    pub fn new() -> Self {
+        Executor::default()
+    }
+
+    pub fn spawn<F: Future<Output = ()> + 'static>(&mut self, future: F) {
+        self.ready.lock().unwrap().push(self.tasks.len());
+        self.tasks.push(Some(Box::pin(future)));
+    }
+
+    // Polls woken tasks until none are ready, returning how many tasks have
+    // not yet finished.
+    pub fn run_until_stalled(&mut self) -> usize {
+        loop {
+            let ready = std::mem::take(&mut *self.ready.lock().unwrap());
+            if ready.is_empty() {
+                return self.tasks.iter().filter(|task| task.is_some()).count();
+            }
+
+            for index in ready {
+                let task = match &mut self.tasks[index] {
+                    Some(task) => task,
+                    None => continue
+                };
+
+                let waker = Waker::from(Arc::new(TaskWaker { task: index, ready: self.ready.clone() }));
+                let mut cx = Context::from_waker(&waker);
+
+                if task.as_mut().poll(&mut cx).is_ready() {
+                    self.tasks[index] = None;
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_executor_channels() {
+    let (sender, mut receiver) = channel();
+    let (done, mut results) = channel();
+
+    let mut executor = Executor::new();
+    executor.spawn(async move {
+        let mut total = 0;
+        while let Some(value) = next(&mut receiver).await {
+            total += value;
+        }
+
+        done.send(total);
+    });
+
+    sender.send(3);
+    assert_eq!(executor.run_until_stalled(), 1);
+
+    sender.send(4);
+    drop(sender);
+    assert_eq!(executor.run_until_stalled(), 0);
+    assert_eq!(block_on(next(&mut results)), Some(7));
+}
@@ -0,0 +1,234 @@
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::ops::Index;
+use std::rc::Rc;
+
+use crate::{Action, DenseMemory, Machine, Memory, Word};
+
+// How many values make up a record, optionally naming each of them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schema {
+    arity: usize,
+    fields: Vec<String>
+}
+
+impl Schema {
+    pub fn fixed(arity: usize) -> Self {
+        assert!(arity > 0, "A record needs at least one value");
+        Schema { arity, fields: Vec::new() }
+    }
+
+    pub fn named(fields: &[&str]) -> Self {
+        let mut schema = Schema::fixed(fields.len());
+        schema.fields = fields.iter().map(|field| field.to_string()).collect();
+        schema
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    pub fn field(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|field| field == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record<W = isize> {
+    schema: Rc<Schema>,
+    values: Vec<W>
+}
+
+impl<W> Record<W> {
+    pub fn get(&self, field: &str) -> Option<&W> {
+        self.schema.field(field).map(|index| &self.values[index])
+    }
+
+    pub fn values(&self) -> &[W] {
+        &self.values
+    }
+
+    pub fn into_values(self) -> Vec<W> {
+        self.values
+    }
+}
+
+impl<W> Index<usize> for Record<W> {
+    type Output = W;
+
+    fn index(&self, index: usize) -> &W {
+        &self.values[index]
+    }
+}
+
+impl<W> Index<&str> for Record<W> {
+    type Output = W;
+
+    fn index(&self, field: &str) -> &W {
+        self.get(field).unwrap_or_else(|| panic!("No field named {}", field))
+    }
+}
+
+// The values a program output before halting partway through a record.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TruncatedFrame<W = isize> {
+    pub arity: usize,
+    pub values: Vec<W>
+}
+
+impl<W: Word> fmt::Display for TruncatedFrame<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values = self.values.iter().map(|value| value.to_string()).collect::<Vec<_>>();
+        write!(f, "program halted after {} of {} values in a record: {}", values.len(), self.arity, values.join(","))
+    }
+}
+
+impl<W: Word> Error for TruncatedFrame<W> {}
+
+// Collects output values one at a time into records.
+#[derive(Clone, Debug)]
+pub struct Framer<W = isize> {
+    schema: Rc<Schema>,
+    buffer: Vec<W>
+}
+
+impl<W> Framer<W> {
+    pub fn new(schema: Schema) -> Self {
+        Framer { schema: Rc::new(schema), buffer: Vec::new() }
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    // Values of the record still being collected.
+    pub fn pending(&self) -> &[W] {
+        &self.buffer
+    }
+
+    // Adds a value, returning the record it completes.
+    pub fn push(&mut self, value: W) -> Option<Record<W>> {
+        self.buffer.push(value);
+
+        if self.buffer.len() < self.schema.arity {
+            return None;
+        }
+
+        Some(Record { schema: Rc::clone(&self.schema), values: mem::take(&mut self.buffer) })
+    }
+
+    // Ends the output, returning any incomplete record.
+    pub fn finish(&mut self) -> Option<TruncatedFrame<W>> {
+        if self.buffer.is_empty() {
+            None
+        } else {
+            Some(TruncatedFrame { arity: self.schema.arity, values: mem::take(&mut self.buffer) })
+        }
+    }
+}
+
+// Wraps a machine whose output is a sequence of records.
+pub struct FramedMachine<W: Word = isize, M: Memory<W> = DenseMemory<W>> {
+    machine: Machine<W, M>,
+    framer: Framer<W>,
+    halted: bool
+}
+
+impl<W: Word, M: Memory<W>> FramedMachine<W, M> {
+    pub fn new(machine: Machine<W, M>, schema: Schema) -> Self {
+        FramedMachine { machine, framer: Framer::new(schema), halted: false }
+    }
+
+    pub fn machine(&self) -> &Machine<W, M> {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut Machine<W, M> {
+        &mut self.machine
+    }
+
+    pub fn into_machine(self) -> Machine<W, M> {
+        self.machine
+    }
+
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn push_input(&mut self, value: W) {
+        self.machine.push_input(value);
+    }
+
+    // Runs until the program completes a record. Returns None once it halts,
+    // waits for input or runs out of budget, after reporting any record it
+    // left unfinished on halting. A record interrupted by a wait for input
+    // carries on when the machine is run again.
+    pub fn read_record(&mut self) -> Option<Result<Record<W>, TruncatedFrame<W>>> {
+        while !self.halted {
+            match self.machine.run() {
+                Action::Output(value) => if let Some(record) = self.framer.push(value) {
+                    return Some(Ok(record));
+                },
+
+                Action::RequiresInput | Action::BudgetExhausted => return None,
+                Action::Halt => self.halted = true
+            }
+        }
+
+        self.framer.finish().map(Err)
+    }
+}
+
+// Stops at the same points as `read_record`, so after supplying more input
+// the iterator can be used again.
+impl<W: Word, M: Memory<W>> Iterator for FramedMachine<W, M> {
+    type Item = Result<Record<W>, TruncatedFrame<W>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record()
+    }
+}
+
+impl<W: Word, M: Memory<W>> Machine<W, M> {
+    pub fn into_framed(self, schema: Schema) -> FramedMachine<W, M> {
+        FramedMachine::new(self, schema)
+    }
+}
+
+#[test]
+fn test_framed_machine() {
+    let program = crate::assemble("
+        OUT #1
+        OUT #2
+        IN -> [0]
+        OUT #3
+        OUT #4
+        OUT #5
+        HALT
+    ").unwrap();
+
+    let mut machine = Machine::new(&program).into_framed(Schema::named(&["x", "y"]));
+    let record = machine.read_record().unwrap().unwrap();
+    assert_eq!((record["x"], record["y"], record[1]), (1, 2, 2));
+    assert_eq!(record.get("z"), None);
+
+    assert_eq!(machine.read_record(), None);
+    assert!(!machine.halted());
+
+    machine.push_input(0);
+    assert_eq!(machine.next().map(|record| record.map(Record::into_values)), Some(Ok(vec![3, 4])));
+    assert_eq!(machine.next(), Some(Err(TruncatedFrame { arity: 2, values: vec![5] })));
+    assert_eq!(machine.next(), None);
+    assert!(machine.halted());
+}
+
+#[test]
+fn test_framer() {
+    let mut framer = Framer::new(Schema::fixed(3));
+    assert_eq!(framer.push(1), None);
+    assert_eq!(framer.push(2), None);
+    assert_eq!(framer.pending(), [1, 2]);
+    assert_eq!(framer.push(3).map(Record::into_values), Some(vec![1, 2, 3]));
+    assert_eq!(framer.finish(), None);
+}
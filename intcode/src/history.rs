@@ -0,0 +1,263 @@
+use std::collections::VecDeque;
+
+use crate::{Machine, Memory, TraceEvent, Word};
+
+#[derive(Clone, Debug)]
+struct UndoRecord<W> {
+    executed: bool,
+    ip: usize,
+    relative_base: W,
+    write: Option<(usize, W, usize)>,
+    input: Option<W>,
+    output: Option<W>
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct History<W = isize> {
+    records: VecDeque<UndoRecord<W>>,
+    // The record of the step being run, kept only if it executes.
+    pending: Option<UndoRecord<W>>,
+    limit: Option<usize>
+}
+
+impl<W: Word> History<W> {
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn outputs(&self) -> Vec<W> {
+        self.records.iter()
+            .filter_map(|record| record.output.clone())
+            .collect()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.records.clear();
+        self.pending = None;
+    }
+
+    fn push(&mut self, record: UndoRecord<W>) {
+        if self.limit == Some(0) {
+            return;
+        }
+
+        while self.limit.is_some_and(|limit| self.records.len() >= limit) {
+            self.records.pop_front();
+        }
+
+        self.records.push_back(record);
+    }
+}
+
+impl<W: Word, M: Memory<W>> Machine<W, M> {
+    // Keeps an undo log of every step so execution can be reversed. With a
+    // limit, only the most recent `limit` steps can be undone.
+    pub fn start_history(&mut self, limit: Option<usize>) {
+        self.history = Some(History { records: VecDeque::new(), pending: None, limit });
+    }
+
+    pub fn stop_history(&mut self) {
+        self.history = None;
+    }
+
+    pub fn history(&self) -> Option<&History<W>> {
+        self.history.as_ref()
+    }
+
+    pub(crate) fn remember(&mut self, event: &TraceEvent<W>) {
+        let history = match &mut self.history {
+            Some(history) => history,
+            None => return
+        };
+
+        let memory = &self.memory;
+        let previous_write = |address: usize| {
+            (address, memory.read(address), memory.extent())
+        };
+
+        match event {
+            TraceEvent::Execute { ip, .. } => history.pending = Some(UndoRecord {
+                executed: true,
+                ip: *ip,
+                relative_base: self.relative_base.clone(),
+                write: None,
+                input: None,
+                output: None
+            }),
+
+            TraceEvent::Poke { address, .. } => history.push(UndoRecord {
+                executed: false,
+                ip: self.ip,
+                relative_base: self.relative_base.clone(),
+                write: Some(previous_write(*address)),
+                input: None,
+                output: None
+            }),
+
+            TraceEvent::Write { address, .. } => if let Some(record) = &mut history.pending {
+                record.write = Some(previous_write(*address));
+            },
+
+            TraceEvent::Input(value) => if let Some(record) = &mut history.pending {
+                record.input = Some(value.clone());
+            },
+
+            TraceEvent::Output(value) => if let Some(record) = &mut history.pending {
+                record.output = Some(value.clone());
+            }
+        }
+    }
+
+    // Keeps the record of the step just run if it executed, and drops it if
+    // the step faulted or stopped to wait for input.
+    pub(crate) fn finish_step(&mut self, executed: bool) {
+        if let Some(history) = &mut self.history {
+            if let Some(record) = history.pending.take().filter(|_| executed) {
+                history.push(record);
+            }
+        }
+    }
+
+    fn undo(&mut self) -> Option<UndoRecord<W>> {
+        let record = self.history.as_mut()?.records.pop_back()?;
+
+        if let Some((address, value, extent)) = &record.write {
+            self.memory.write(*address, value.clone());
+            self.memory.truncate_extent(*extent);
+            self.cache.invalidate(*address);
+        }
+
+        if let Some(value) = &record.input {
+            self.inputs.insert(0, value.clone());
+        }
+
+        self.ip = record.ip;
+        self.instruction_ip = record.ip;
+        self.relative_base = record.relative_base.clone();
+
+        Some(record)
+    }
+
+    // Undoes the most recently executed instruction, along with any pokes
+    // made since. Returns false once there is no history left to undo.
+    pub fn step_back(&mut self) -> bool {
+        while let Some(record) = self.undo() {
+            if record.executed {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    // Rewinds to just before the most recent write to `address`.
+    pub fn run_back_to_write(&mut self, address: usize) -> bool {
+        while let Some(record) = self.undo() {
+            if record.write.as_ref().map(|(written, _, _)| *written) == Some(address) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    // Rewinds to just before the most recent output, returning its value.
+    pub fn rewind_to_output(&mut self) -> Option<W> {
+        while let Some(record) = self.undo() {
+            if record.output.is_some() {
+                return record.output;
+            }
+        }
+
+        None
+    }
+}
+
+#[test]
+fn test_step_back() {
+    use crate::{intcode_parser, Action, IntcodeProgram, Parameter};
+
+    let program: IntcodeProgram = intcode_parser(include_str!("../../input/2019/day9.txt")).unwrap();
+
+    let mut machine = Machine::new(&program);
+    machine.start_history(None);
+    machine.write(1, &Parameter::Position(2000));
+    machine.push_input(1);
+    assert_eq!(machine.run(), Action::Output(3460311188));
+    assert_eq!(machine.run(), Action::Halt);
+
+    assert_eq!(machine.history().unwrap().outputs(), vec![3460311188]);
+    assert_eq!(machine.rewind_to_output(), Some(3460311188));
+    assert_eq!(machine.ip(), 901);
+
+    assert!(machine.run_back_to_write(63));
+    assert_eq!(machine.memory()[63], 0);
+
+    while machine.step_back() {}
+    assert_eq!(machine.ip(), 0);
+    assert_eq!(machine.relative_base(), 0);
+    assert_eq!(machine.inputs(), &[1]);
+    assert_eq!(machine.memory().as_slice(), &program[..]);
+
+    let mut paged = Machine::with_memory(crate::PagedMemory::from_program(&program));
+    paged.start_history(None);
+    paged.write(1, &Parameter::Position(1 << 40));
+    paged.push_input(1);
+    assert_eq!(paged.run(), Action::Output(3460311188));
+
+    while paged.step_back() {}
+    assert_eq!(paged.memory().extent(), program.len());
+    assert_eq!(paged.memory().read(1 << 40), 0);
+
+    assert_eq!(machine.run(), Action::Output(3460311188));
+}
+
+#[test]
+fn test_history_limit() {
+    use crate::{intcode_parser, IntcodeProgram};
+
+    // Counts down from 5 in a loop.
+    let program: IntcodeProgram = intcode_parser("1101,0,5,12,1001,12,-1,12,1005,12,4,99,0").unwrap();
+
+    for limit in 0..=2 {
+        let mut machine = Machine::new(&program);
+        machine.start_history(Some(limit));
+        machine.run();
+        assert_eq!(machine.history().unwrap().len(), limit);
+
+        let mut undone = 0;
+        while machine.step_back() {
+            undone += 1;
+        }
+        assert_eq!(undone, limit);
+    }
+}
+
+#[test]
+fn test_history_skips_stalled_steps() {
+    use crate::{intcode_parser, Action, IntcodeProgram};
+
+    // Echoes one input, then jumps out of the program.
+    let program: IntcodeProgram = intcode_parser("3,7,4,7,1105,1,-1,0").unwrap();
+
+    let mut machine = Machine::new(&program);
+    machine.start_history(Some(2));
+    assert_eq!(machine.run(), Action::RequiresInput);
+    assert_eq!(machine.run(), Action::RequiresInput);
+    assert_eq!(machine.history().unwrap().len(), 0);
+
+    machine.push_input(5);
+    assert_eq!(machine.run(), Action::Output(5));
+    assert!(machine.try_run().is_err());
+    assert_eq!(machine.history().unwrap().len(), 2);
+
+    assert!(machine.step_back());
+    assert_eq!(machine.ip(), 2);
+    assert!(machine.step_back());
+    assert_eq!(machine.ip(), 0);
+    assert_eq!(machine.inputs(), &[5]);
+}
@@ -0,0 +1,197 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+use std::sync::mpsc;
+
+use crate::encoding::invalid_data;
+use crate::{Action, IntcodeError, Machine, Memory, Word};
+
+// Supplies input to a machine driven by `run_with`. Returning None pauses
+// the machine with `Action::RequiresInput`.
+pub trait InputSource<W = isize> {
+    fn next_input(&mut self) -> Option<W>;
+}
+
+// Receives every value a machine driven by `run_with` outputs.
+pub trait OutputSink<W = isize> {
+    fn write_output(&mut self, value: W);
+}
+
+impl<W, F: FnMut() -> Option<W>> InputSource<W> for F {
+    fn next_input(&mut self) -> Option<W> {
+        self()
+    }
+}
+
+impl<W> InputSource<W> for VecDeque<W> {
+    fn next_input(&mut self) -> Option<W> {
+        self.pop_front()
+    }
+}
+
+// Blocks until a value arrives, or gives up once every sender has gone.
+impl<W> InputSource<W> for mpsc::Receiver<W> {
+    fn next_input(&mut self) -> Option<W> {
+        self.recv().ok()
+    }
+}
+
+// Reads one value per line, skipping blank lines. Input ends at the end of
+// the reader or at the first line that cannot be read or parsed, which
+// `take_error` then returns.
+pub struct LineSource<R> {
+    reader: R,
+    error: Option<io::Error>
+}
+
+impl<R: BufRead> LineSource<R> {
+    pub fn new(reader: R) -> Self {
+        LineSource { reader, error: None }
+    }
+
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+}
+
+impl LineSource<io::StdinLock<'static>> {
+    pub fn stdin() -> Self {
+        LineSource::new(io::stdin().lock())
+    }
+}
+
+impl<W: Word, R: BufRead> InputSource<W> for LineSource<R> {
+    fn next_input(&mut self) -> Option<W> {
+        if self.error.is_some() {
+            return None;
+        }
+
+        loop {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) => {},
+                Err(error) => {
+                    self.error = Some(error);
+                    return None;
+                }
+            }
+
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            match line.parse() {
+                Ok(value) => return Some(value),
+                Err(_) => {
+                    self.error = Some(invalid_data(&format!("not a number: {}", line)));
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+pub struct IterSource<I>(I);
+
+impl<I: Iterator> IterSource<I> {
+    pub fn new<T: IntoIterator<IntoIter = I>>(values: T) -> Self {
+        IterSource(values.into_iter())
+    }
+}
+
+impl<W, I: Iterator<Item = W>> InputSource<W> for IterSource<I> {
+    fn next_input(&mut self) -> Option<W> {
+        self.0.next()
+    }
+}
+
+impl<W, F: FnMut(W)> OutputSink<W> for F {
+    fn write_output(&mut self, value: W) {
+        self(value);
+    }
+}
+
+impl<W> OutputSink<W> for Vec<W> {
+    fn write_output(&mut self, value: W) {
+        self.push(value);
+    }
+}
+
+impl<W> OutputSink<W> for VecDeque<W> {
+    fn write_output(&mut self, value: W) {
+        self.push_back(value);
+    }
+}
+
+// Outputs sent after the receiver has gone are dropped.
+impl<W> OutputSink<W> for mpsc::Sender<W> {
+    fn write_output(&mut self, value: W) {
+        self.send(value).ok();
+    }
+}
+
+impl<W: Word> OutputSink<W> for io::Stdout {
+    fn write_output(&mut self, value: W) {
+        println!("{}", value);
+    }
+}
+
+impl<W: Word, M: Memory<W>> Machine<W, M> {
+    pub fn run_with<I: InputSource<W>, O: OutputSink<W>>(&mut self, source: &mut I, sink: &mut O) -> Action<W> {
+        self.try_run_with(source, sink).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    // Runs until the program halts or needs input the source cannot provide,
+    // passing each output to the sink. The returned action is `Halt`,
+    // `RequiresInput` or `BudgetExhausted`.
+    pub fn try_run_with<I: InputSource<W>, O: OutputSink<W>>(&mut self, source: &mut I, sink: &mut O) -> Result<Action<W>, IntcodeError<W>> {
+        loop {
+            match self.try_run()? {
+                Action::Output(value) => sink.write_output(value),
+
+                Action::RequiresInput => match source.next_input() {
+                    Some(value) => self.push_input(value),
+                    None => return Ok(Action::RequiresInput)
+                },
+
+                action => return Ok(action)
+            }
+        }
+    }
+}
+
+#[test]
+fn test_run_with() {
+    use std::thread;
+    use crate::{intcode_parser, IntcodeProgram};
+
+    // Outputs the sum of each pair of inputs.
+    let program: IntcodeProgram = intcode_parser("3,13,3,14,1,13,14,15,4,15,1105,1,0,0,0,0").unwrap();
+
+    let mut outputs = Vec::new();
+    let mut machine = Machine::new(&program);
+    assert_eq!(machine.run_with(&mut IterSource::new(vec![1, 2, 3]), &mut outputs), Action::RequiresInput);
+    assert_eq!(outputs, vec![3]);
+
+    let mut pending = VecDeque::from(vec![4, 5]);
+    assert_eq!(machine.run_with(&mut pending, &mut |value| outputs.push(value * 10)), Action::RequiresInput);
+    assert_eq!(outputs, vec![3, 70]);
+
+    let mut lines = LineSource::new(&b"1\n\n2\n3\nx\n4\n"[..]);
+    let mut outputs = Vec::new();
+    assert_eq!(Machine::new(&program).run_with(&mut lines, &mut outputs), Action::RequiresInput);
+    assert_eq!(outputs, vec![3]);
+    assert_eq!(lines.take_error().map(|error| error.kind()), Some(io::ErrorKind::InvalidData));
+
+    let (mut input, receiver) = mpsc::channel();
+    let (mut sender, output) = mpsc::channel();
+    let worker = thread::spawn(move || Machine::new(&program).run_with(&mut { receiver }, &mut sender));
+
+    input.write_output(20);
+    input.write_output(22);
+    assert_eq!(output.recv(), Ok(42));
+
+    drop(input);
+    assert_eq!(worker.join().unwrap(), Action::RequiresInput);
+}
@@ -1,268 +1,776 @@
+mod ascii;
+mod assembler;
+mod async_machine;
+mod binary;
+mod cache;
+mod cfg;
+mod coverage;
+mod decompiler;
+mod disassembler;
+mod drivers;
+mod encoding;
+mod error;
+mod executor;
+mod framing;
+mod history;
+mod io;
+mod memory;
+mod network;
+mod parser;
+mod pipeline;
+mod profile;
+mod snapshot;
+mod trace;
+mod word;
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use cache::InstructionCache;
+
+pub use ascii::{AsciiMachine, AsciiOutput};
+pub use assembler::{assemble, assemble_with_labels, AssemblerError};
+pub use async_machine::AsyncMachine;
+pub use binary::BinaryProgram;
+pub use cfg::{Block, Cfg, Exit, Target};
+pub use coverage::Coverage;
+pub use decompiler::decompile;
+pub use disassembler::{disassemble, listing, Line};
+pub use drivers::Outputs;
+pub use error::IntcodeError;
+pub use executor::{block_on, channel, next, Executor, Next, Receiver, Sender};
+pub use framing::{FramedMachine, Framer, Record, Schema, TruncatedFrame};
+pub use futures_core::Stream;
+pub use history::History;
+pub use io::{InputSource, IterSource, LineSource, OutputSink};
+pub use memory::{DenseMemory, Memory, PagedMemory};
+pub use network::{Network, NetworkEvent, Packet};
+pub use parser::{intcode_parser, ParseError};
+pub use pipeline::Pipeline;
+pub use profile::{Loop, Profile};
+pub use snapshot::{SaveError, Snapshot};
+pub use trace::{replay, Divergence, Trace, TraceEvent};
+pub use word::Word;
+
+pub type IntcodeProgram<W = isize> = Vec<W>;
+pub type IntcodeMemory<W = isize> = Vec<W>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Parameter<W = isize> {
+    Position(usize),
+    Immediate(W),
+    Relative(W)
+}
 
-pub type IntcodeProgram = Vec<isize>;
-pub type IntcodeMemory = Vec<isize>;
+impl<W: Word> Parameter<W> {
+    pub fn new(mode: isize, value: W) -> Option<Parameter<W>> {
+        match mode {
+            0 => value.to_usize().map(Parameter::Position),
+            1 => Some(Parameter::Immediate(value)),
+            2 => Some(Parameter::Relative(value)),
+            _ => None
+        }
+    }
 
-pub fn intcode_parser(input: &str) -> IntcodeProgram {
-    input.trim()
-      .split(",")
-      .map(|s| s.parse::<isize>().unwrap())
-      .collect::<IntcodeProgram>()
+    // None for an immediate, or a relative address that does not fit in a word.
+    pub fn address(&self, relative_base: &W) -> Option<W> {
+        match self {
+            Parameter::Immediate(_) => None,
+            Parameter::Position(position) => Some(W::from_usize(*position)),
+            Parameter::Relative(offset) => relative_base.checked_add(offset),
+        }
+    }
+
+    pub fn resolve<M: Memory<W>>(&self, memory: &M, relative_base: &W) -> Option<W> {
+        match self {
+            Parameter::Immediate(value) => Some(value.clone()),
+            _ => Some(memory.read(self.address(relative_base)?.to_usize()?))
+        }
+    }
 }
 
-#[derive(Debug)]
-pub enum Parameter {
-    Position(usize),
-    Immediate(isize),
-    Relative(isize)
+#[derive(Clone, Debug, PartialEq)]
+pub enum Instruction<W = isize> {
+    Add(Parameter<W>, Parameter<W>, Parameter<W>),
+    Multiply(Parameter<W>, Parameter<W>, Parameter<W>),
+    Input(Parameter<W>),
+    Output(Parameter<W>),
+    JumpIfTrue(Parameter<W>, Parameter<W>),
+    JumpIfFalse(Parameter<W>, Parameter<W>),
+    LessThan(Parameter<W>, Parameter<W>, Parameter<W>),
+    Equals(Parameter<W>, Parameter<W>, Parameter<W>),
+    AdjustRelativeBase(Parameter<W>),
+    Halt
 }
 
-impl Parameter {
-    pub fn new(mode: isize, value: isize) -> Parameter {
-        match mode {
-            0 => Parameter::Position(value as usize),
-            1 => Parameter::Immediate(value),
-            2 => Parameter::Relative(value),
-            _ => unimplemented!()
+impl<W: Word> Instruction<W> {
+    pub fn decode<M: Memory<W>>(memory: &M, ip: usize) -> Result<Instruction<W>, IntcodeError<W>> {
+        let word = |offset: usize| memory.read(ip + offset);
+
+        let instruction_value = word(0);
+        let invalid_opcode = || IntcodeError::InvalidOpcode { ip, instruction: instruction_value.clone() };
+
+        let code = instruction_value.to_isize().ok_or_else(invalid_opcode)?;
+        let opcode      = code % 100;
+        let first_mode  = (code / 100) % 10;
+        let second_mode = (code / 1000) % 10;
+        let third_mode  = (code / 10000) % 10;
+
+        let parameter = |offset: usize, mode: isize| {
+            let value = word(offset);
+            let instruction = instruction_value.clone();
+
+            match mode {
+                0 if value.is_negative() => Err(IntcodeError::NegativeAddress { ip, instruction, address: value }),
+                0 if value.to_usize().is_none() => Err(IntcodeError::AddressOutOfRange { ip, instruction, address: value }),
+                _ => Parameter::new(mode, value).ok_or(IntcodeError::InvalidMode { ip, instruction, mode })
+            }
+        };
+
+        let instruction = match opcode {
+            1 => Instruction::Add(
+                parameter(1, first_mode)?,
+                parameter(2, second_mode)?,
+                parameter(3, third_mode)?,
+            ),
+
+            2 => Instruction::Multiply(
+                parameter(1, first_mode)?,
+                parameter(2, second_mode)?,
+                parameter(3, third_mode)?,
+            ),
+
+            3 => Instruction::Input(
+                parameter(1, first_mode)?
+            ),
+
+            4 => Instruction::Output(
+                parameter(1, first_mode)?
+            ),
+
+            5 => Instruction::JumpIfTrue(
+                parameter(1, first_mode)?,
+                parameter(2, second_mode)?
+            ),
+
+            6 => Instruction::JumpIfFalse(
+                parameter(1, first_mode)?,
+                parameter(2, second_mode)?
+            ),
+
+            7 => Instruction::LessThan(
+                parameter(1, first_mode)?,
+                parameter(2, second_mode)?,
+                parameter(3, third_mode)?
+            ),
+
+            8 => Instruction::Equals(
+                parameter(1, first_mode)?,
+                parameter(2, second_mode)?,
+                parameter(3, third_mode)?
+            ),
+
+            9 => Instruction::AdjustRelativeBase(
+                parameter(1, first_mode)?
+            ),
+
+            99 => Instruction::Halt,
+
+            _ => return Err(invalid_opcode())
+        };
+
+        Ok(instruction)
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Add(..) => "ADD",
+            Instruction::Multiply(..) => "MUL",
+            Instruction::Input(..) => "IN",
+            Instruction::Output(..) => "OUT",
+            Instruction::JumpIfTrue(..) => "JT",
+            Instruction::JumpIfFalse(..) => "JF",
+            Instruction::LessThan(..) => "LT",
+            Instruction::Equals(..) => "EQ",
+            Instruction::AdjustRelativeBase(..) => "ARB",
+            Instruction::Halt => "HALT",
         }
     }
 
-    pub fn resolve(&self, memory: &IntcodeMemory, relative_base: isize) -> isize {
+    pub fn parameters(&self) -> Vec<&Parameter<W>> {
         match self {
-            Parameter::Immediate(value) => *value,
-            Parameter::Position(position) => *memory.get(*position).unwrap_or(&0),
-            Parameter::Relative(position) => *memory.get((relative_base + *position) as usize).unwrap_or(&0),
+            Instruction::Add(a, b, c) |
+            Instruction::Multiply(a, b, c) |
+            Instruction::LessThan(a, b, c) |
+            Instruction::Equals(a, b, c) => vec![a, b, c],
+
+            Instruction::JumpIfTrue(a, b) |
+            Instruction::JumpIfFalse(a, b) => vec![a, b],
+
+            Instruction::Input(a) |
+            Instruction::Output(a) |
+            Instruction::AdjustRelativeBase(a) => vec![a],
+
+            Instruction::Halt => vec![],
+        }
+    }
+
+    pub fn output(&self) -> Option<&Parameter<W>> {
+        match self {
+            Instruction::Add(_, _, output) |
+            Instruction::Multiply(_, _, output) |
+            Instruction::LessThan(_, _, output) |
+            Instruction::Equals(_, _, output) |
+            Instruction::Input(output) => Some(output),
+            _ => None
+        }
+    }
+
+    // Matched out rather than counted from `parameters`, which allocates,
+    // since the machine asks for it on every instruction.
+    pub fn size(&self) -> usize {
+        match self {
+            Instruction::Add(..) |
+            Instruction::Multiply(..) |
+            Instruction::LessThan(..) |
+            Instruction::Equals(..) => 4,
+
+            Instruction::JumpIfTrue(..) |
+            Instruction::JumpIfFalse(..) => 3,
+
+            Instruction::Input(..) |
+            Instruction::Output(..) |
+            Instruction::AdjustRelativeBase(..) => 2,
+
+            Instruction::Halt => 1,
         }
     }
 }
 
-#[derive(Debug)]
-pub enum Instruction {
-    Add(Parameter, Parameter, Parameter),
-    Multiply(Parameter, Parameter, Parameter),
-    Input(Parameter),
-    Output(Parameter),
-    JumpIfTrue(Parameter, Parameter),
-    JumpIfFalse(Parameter, Parameter),
-    LessThan(Parameter, Parameter, Parameter),
-    Equals(Parameter, Parameter, Parameter),
-    AdjustRelativeBase(Parameter),
-    Halt
+impl<W: Word> fmt::Display for Parameter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Parameter::Position(position) => write!(f, "[{}]", position),
+            Parameter::Immediate(value) => write!(f, "#{}", value),
+            Parameter::Relative(offset) if offset.is_negative() => write!(f, "[rb{}]", offset),
+            Parameter::Relative(offset) => write!(f, "[rb+{}]", offset),
+        }
+    }
 }
 
-pub enum Action {
+impl<W: Word> fmt::Display for Instruction<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.mnemonic())?;
+
+        let mut parameters = self.parameters();
+        if self.output().is_some() {
+            parameters.pop();
+        }
+
+        for (i, parameter) in parameters.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { " " } else { ", " }, parameter)?;
+        }
+
+        if let Some(output) = self.output() {
+            write!(f, " -> {}", output)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Action<W = isize> {
     RequiresInput,
-    Output(isize),
-    Halt
+    Output(W),
+    Halt,
+    // The instruction budget or time limit ran out; the machine can carry on
+    // once it is raised.
+    BudgetExhausted
+}
+
+// What an instruction does, worked out by `Machine::plan` before any of it is
+// applied.
+enum Effect<W> {
+    Store(usize, W),
+    // Where input goes; an error only matters once there is input to store.
+    Input(Result<usize, IntcodeError<W>>),
+    Output(W),
+    Jump { address: usize, immediate: bool },
+    AdjustRelativeBase(W),
+    Halt,
+    Continue
+}
+
+struct Plan<W> {
+    effect: Effect<W>,
+    // Addresses of the operands read, for the profiler.
+    reads: [Option<usize>; 2]
 }
 
-pub struct Machine {
-    memory: IntcodeMemory,
+// How many instructions `run` executes between checks of the time limit.
+const TIME_LIMIT_INTERVAL: u64 = 1024;
+
+#[derive(Clone)]
+pub struct Machine<W: Word = isize, M: Memory<W> = DenseMemory<W>> {
+    memory: M,
     ip: usize,
-    inputs: Vec<isize>,
-    relative_base: isize
+    instruction_ip: usize,
+    inputs: Vec<W>,
+    relative_base: W,
+    trace: Option<Trace<W>>,
+    history: Option<History<W>>,
+    profile: Option<Profile>,
+    coverage: Option<Coverage>,
+    cache: InstructionCache<W>,
+    checked_arithmetic: bool,
+    budget: Option<u64>,
+    deadline: Option<Instant>
 }
 
 impl Machine {
     pub fn new(memory: &IntcodeMemory) -> Self {
+        Machine::with_memory(DenseMemory::from_program(memory))
+    }
+}
+
+impl<W: Word, M: Memory<W>> Machine<W, M> {
+    pub fn with_memory(memory: M) -> Self {
         Machine {
-            memory: memory.clone(), 
+            memory,
             ip: 0,
+            instruction_ip: 0,
             inputs: Vec::new(),
-            relative_base: 0
+            relative_base: W::default(),
+            trace: None,
+            history: None,
+            profile: None,
+            coverage: None,
+            cache: InstructionCache::new(),
+            checked_arithmetic: false,
+            budget: None,
+            deadline: None
         }
     }
 
-    fn read(&mut self) -> isize {
-        let value = self.memory.get(self.ip);
-        self.ip += 1;
-        *value.unwrap_or(&0)
+    pub fn ip(&self) -> usize {
+        self.ip
     }
 
-    fn resolve(&self, parameter: &Parameter) -> isize {
-        parameter.resolve(&self.memory, self.relative_base)
+    pub fn relative_base(&self) -> W {
+        self.relative_base.clone()
     }
 
-    fn jump(&mut self, address: usize) {
-        self.ip = address;
+    pub fn set_relative_base(&mut self, relative_base: W) {
+        self.relative_base = relative_base;
     }
 
-    fn read_input(&mut self) -> Option<isize> {
-        if self.inputs.len() > 0 {
-            Some(self.inputs.remove(0))
-        } else {
-            None
-        }
+    pub fn memory(&self) -> &M {
+        &self.memory
     }
 
-    pub fn push_input(&mut self, input: isize) {
-        self.inputs.push(input);
+    pub fn inputs(&self) -> &[W] {
+        &self.inputs
     }
 
-    pub fn write(&mut self, value: isize, parameter: &Parameter) {
-        let address = match parameter {
-            Parameter::Position(position) => *position,
-            Parameter::Relative(position) => (self.relative_base + *position) as usize,
-            Parameter::Immediate(_) => unimplemented!()
-        };
+    // With checked arithmetic, additions, multiplications and relative base
+    // adjustments that overflow the word type fault instead of wrapping.
+    pub fn set_checked_arithmetic(&mut self, checked: bool) {
+        self.checked_arithmetic = checked;
+    }
+
+    // Limits `run` to the given number of further instructions, after which
+    // it returns `Action::BudgetExhausted` until the budget is raised again.
+    pub fn set_budget(&mut self, instructions: Option<u64>) {
+        self.budget = instructions;
+    }
+
+    // The number of instructions left in the budget.
+    pub fn budget(&self) -> Option<u64> {
+        self.budget
+    }
+
+    // Makes `run` return `Action::BudgetExhausted` once `limit` has passed,
+    // counting from now.
+    pub fn set_time_limit(&mut self, limit: Option<Duration>) {
+        self.deadline = limit.map(|limit| Instant::now() + limit);
+    }
 
-        if address >= self.memory.len() {
-            self.memory.resize(address+1, 0);
+    // Whether `run` would stop straight away for want of budget or time.
+    pub(crate) fn exhausted(&self) -> bool {
+        self.budget == Some(0) || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    // Whether the last instruction run was a halt, so running on would
+    // carry on past the end of the program.
+    pub fn halted(&self) -> bool {
+        self.ip == self.instruction_ip + 1
+            && matches!(Instruction::decode(&self.memory, self.instruction_ip), Ok(Instruction::Halt))
+    }
+
+    fn instruction_value(&self) -> W {
+        self.memory.read(self.instruction_ip)
+    }
+
+    fn add(&self, lhs: &W, rhs: &W) -> Result<W, IntcodeError<W>> {
+        if !self.checked_arithmetic {
+            return Ok(lhs.add(rhs));
         }
 
-        self.memory[address] = value;
+        lhs.checked_add(rhs).ok_or_else(|| self.overflow(lhs, rhs))
     }
 
-    fn next_instruction(&mut self) -> Instruction {
-        let instruction_value = self.read();
-        let opcode      = instruction_value % 100;
-        let first_mode  = (instruction_value / 100) % 10;
-        let second_mode = (instruction_value / 1000) % 10;
-        let third_mode  = (instruction_value / 10000) % 10;
+    fn mul(&self, lhs: &W, rhs: &W) -> Result<W, IntcodeError<W>> {
+        if !self.checked_arithmetic {
+            return Ok(lhs.mul(rhs));
+        }
 
-        match opcode {
-            1 => Instruction::Add(
-                Parameter::new(first_mode, self.read()),
-                Parameter::new(second_mode, self.read()),
-                Parameter::new(third_mode, self.read()),
-            ),
+        lhs.checked_mul(rhs).ok_or_else(|| self.overflow(lhs, rhs))
+    }
 
-            2 => Instruction::Multiply(
-                Parameter::new(first_mode, self.read()),
-                Parameter::new(second_mode, self.read()),
-                Parameter::new(third_mode, self.read()),
-            ),
+    fn overflow(&self, lhs: &W, rhs: &W) -> IntcodeError<W> {
+        IntcodeError::Overflow {
+            ip: self.instruction_ip,
+            instruction: self.instruction_value(),
+            lhs: lhs.clone(),
+            rhs: rhs.clone()
+        }
+    }
 
-            3 => Instruction::Input(
-                Parameter::new(first_mode, self.read())
-            ),
+    // The value of an operand, along with the address it was read from.
+    fn operand(&self, parameter: &Parameter<W>) -> Result<(W, Option<usize>), IntcodeError<W>> {
+        match parameter {
+            Parameter::Immediate(value) => Ok((value.clone(), None)),
+            _ => {
+                let address = self.address(parameter)?;
+                Ok((self.memory.read(address), Some(address)))
+            }
+        }
+    }
 
-            4 => Instruction::Output(
-                Parameter::new(first_mode, self.read())
-            ),
+    fn jump_target(&self, parameter: &Parameter<W>, target: W) -> Result<Effect<W>, IntcodeError<W>> {
+        match target.to_usize() {
+            Some(address) if address < self.memory.extent() => Ok(Effect::Jump {
+                address,
+                immediate: matches!(parameter, Parameter::Immediate(_))
+            }),
+
+            _ => Err(IntcodeError::JumpOutOfRange {
+                ip: self.instruction_ip,
+                instruction: self.instruction_value(),
+                target
+            })
+        }
+    }
 
-            5 => Instruction::JumpIfTrue(
-                Parameter::new(first_mode, self.read()),
-                Parameter::new(second_mode, self.read())
-            ),
+    fn read_input(&mut self) -> Option<W> {
+        if self.inputs.is_empty() {
+            return None;
+        }
 
-            6 => Instruction::JumpIfFalse(
-                Parameter::new(first_mode, self.read()),
-                Parameter::new(second_mode, self.read())
-            ),
+        let value = self.inputs.remove(0);
+        self.record(TraceEvent::Input(value.clone()));
+        Some(value)
+    }
 
-            7 => Instruction::LessThan(
-                Parameter::new(first_mode, self.read()),
-                Parameter::new(second_mode, self.read()),
-                Parameter::new(third_mode, self.read())
-            ),
+    pub fn push_input(&mut self, input: W) {
+        self.inputs.push(input);
+    }
 
-            8 => Instruction::Equals(
-                Parameter::new(first_mode, self.read()),
-                Parameter::new(second_mode, self.read()),
-                Parameter::new(third_mode, self.read())
-            ),
+    pub fn write(&mut self, value: W, parameter: &Parameter<W>) {
+        self.try_write(value, parameter).unwrap_or_else(|error| panic!("{}", error));
+    }
 
-            9 => Instruction::AdjustRelativeBase(
-                Parameter::new(first_mode, self.read())
-            ),
+    pub fn try_write(&mut self, value: W, parameter: &Parameter<W>) -> Result<(), IntcodeError<W>> {
+        let address = self.address(parameter)?;
+        self.record(TraceEvent::Poke { address, value: value.clone() });
+        self.store(address, value);
+        Ok(())
+    }
 
-            99 => Instruction::Halt,
-            _ => unimplemented!()
+    fn address(&self, parameter: &Parameter<W>) -> Result<usize, IntcodeError<W>> {
+        match (parameter, parameter.address(&self.relative_base)) {
+            (_, Some(address)) if address.is_negative() => Err(IntcodeError::NegativeAddress {
+                ip: self.instruction_ip,
+                instruction: self.instruction_value(),
+                address
+            }),
+
+            (_, Some(address)) => match address.to_usize() {
+                Some(address) => Ok(address),
+                None => Err(IntcodeError::AddressOutOfRange {
+                    ip: self.instruction_ip,
+                    instruction: self.instruction_value(),
+                    address
+                })
+            },
+
+            // The address is checked whether or not arithmetic is.
+            (Parameter::Relative(offset), None) => Err(self.overflow(&self.relative_base, offset)),
+
+            (_, None) => Err(IntcodeError::WriteToImmediate {
+                ip: self.instruction_ip,
+                instruction: self.instruction_value()
+            })
         }
     }
 
-    pub fn step(&mut self) -> Option<Action> {
-        let mut action = None;
+    fn store(&mut self, address: usize, value: W) {
+        if let Some(profile) = &mut self.profile {
+            profile.write(address);
+        }
+
+        self.memory.write(address, value);
+        self.cache.invalidate(address);
+    }
+
+    fn set(&mut self, address: usize, value: W) {
+        self.record(TraceEvent::Write { address, value: value.clone() });
+        self.store(address, value);
+    }
+
+    pub fn step(&mut self) -> Option<Action<W>> {
+        self.try_step().unwrap_or_else(|error| panic!("{}", error))
+    }
 
-        let instruction = self.next_instruction();
+    pub fn try_step(&mut self) -> Result<Option<Action<W>>, IntcodeError<W>> {
+        let result = self.execute();
+        self.finish_step(matches!(result, Ok(None | Some(Action::Output(_) | Action::Halt))));
 
-        match instruction {
+        match (&result, &mut self.budget) {
+            (Err(_), _) => self.ip = self.instruction_ip,
+            (Ok(_), Some(budget)) => *budget = budget.saturating_sub(1),
+            (Ok(_), None) => {}
+        }
+
+        result
+    }
+
+    // Works out what an instruction does without changing anything, so a
+    // cached instruction can be run by reference.
+    fn plan(&self, instruction: &Instruction<W>) -> Result<Plan<W>, IntcodeError<W>> {
+        let mut reads = [None, None];
+        let mut operand = |index: usize, parameter: &Parameter<W>| {
+            let (value, address) = self.operand(parameter)?;
+            reads[index] = address;
+            Ok(value)
+        };
+
+        let effect = match instruction {
             Instruction::Add(lhs, rhs, output) => {
-                let lhs = self.resolve(&lhs);
-                let rhs = self.resolve(&rhs);
-                self.write(lhs + rhs, &output);
+                let value = self.add(&operand(0, lhs)?, &operand(1, rhs)?)?;
+                Effect::Store(self.address(output)?, value)
             },
 
             Instruction::Multiply(lhs, rhs, output) => {
-                let lhs = self.resolve(&lhs);
-                let rhs = self.resolve(&rhs);
-                self.write(lhs * rhs, &output);
+                let value = self.mul(&operand(0, lhs)?, &operand(1, rhs)?)?;
+                Effect::Store(self.address(output)?, value)
             },
 
-            Instruction::Input(output) => {
-                if let Some(value) = self.read_input() {
-                    self.write(value, &output);
-                } else {
-                    action = Some(Action::RequiresInput);
-                    self.ip -= 2;
-                }
-            },
+            Instruction::Input(output) => Effect::Input(self.address(output)),
 
-            Instruction::Output(value) => {
-                action = Some(Action::Output(self.resolve(&value)));
-            }
-
-            Instruction::JumpIfTrue(value, target) => {
-                let value = self.resolve(&value);
+            Instruction::Output(value) => Effect::Output(operand(0, value)?),
 
-                if value != 0 {
-                    let target = self.resolve(&target) as usize;
-                    self.jump(target);
-                }
+            Instruction::JumpIfTrue(value, target) => match operand(0, value)?.is_zero() {
+                false => self.jump_target(target, operand(1, target)?)?,
+                true => Effect::Continue
             },
 
-            Instruction::JumpIfFalse(value, target) => {
-                let value = self.resolve(&value);
-
-                if value == 0 {
-                    let target = self.resolve(&target) as usize;
-                    self.jump(target);
-                }
+            Instruction::JumpIfFalse(value, target) => match operand(0, value)?.is_zero() {
+                true => self.jump_target(target, operand(1, target)?)?,
+                false => Effect::Continue
             },
 
             Instruction::LessThan(lhs, rhs, output) => {
-                let lhs = self.resolve(&lhs);
-                let rhs = self.resolve(&rhs);
-
-                if lhs < rhs {
-                    self.write(1, &output);
-                } else {
-                    self.write(0, &output);
-                }
+                let value = if operand(0, lhs)? < operand(1, rhs)? { 1 } else { 0 };
+                Effect::Store(self.address(output)?, W::from_isize(value))
             },
 
             Instruction::Equals(lhs, rhs, output) => {
-                let lhs = self.resolve(&lhs);
-                let rhs = self.resolve(&rhs);
+                let value = if operand(0, lhs)? == operand(1, rhs)? { 1 } else { 0 };
+                Effect::Store(self.address(output)?, W::from_isize(value))
+            },
+
+            Instruction::AdjustRelativeBase(diff) => {
+                Effect::AdjustRelativeBase(self.add(&self.relative_base, &operand(0, diff)?)?)
+            },
+
+            Instruction::Halt => Effect::Halt,
+        };
+
+        Ok(Plan { effect, reads })
+    }
+
+    fn execute(&mut self) -> Result<Option<Action<W>>, IntcodeError<W>> {
+        let mut action = None;
 
-                if lhs == rhs {
-                    self.write(1, &output);
+        self.instruction_ip = self.ip;
+
+        let (planned, mnemonic, size) = match self.cache.get(self.ip) {
+            Some(instruction) => (self.plan(instruction), instruction.mnemonic(), instruction.size()),
+            None => {
+                let instruction = Instruction::decode(&self.memory, self.ip)?;
+                let planned = (self.plan(&instruction), instruction.mnemonic(), instruction.size());
+                self.cache.insert(self.ip, instruction);
+                planned
+            }
+        };
+
+        self.ip += size;
+
+        self.record(TraceEvent::Execute { ip: self.instruction_ip, instruction: self.instruction_value() });
+
+        if let Some(profile) = &mut self.profile {
+            profile.execute(self.instruction_ip, mnemonic);
+        }
+
+        if let Some(coverage) = &mut self.coverage {
+            coverage.execute(self.instruction_ip);
+        }
+
+        let Plan { effect, reads } = planned?;
+
+        if let Some(profile) = &mut self.profile {
+            for address in reads.iter().flatten() {
+                profile.read(*address);
+            }
+        }
+
+        match effect {
+            Effect::Store(address, value) => self.set(address, value),
+
+            Effect::Input(address) => {
+                if let Some(value) = self.read_input() {
+                    self.set(address?, value);
                 } else {
-                    self.write(0, &output);
+                    action = Some(Action::RequiresInput);
+                    self.ip = self.instruction_ip;
                 }
             },
 
-            Instruction::AdjustRelativeBase(diff) => {
-                let diff = self.resolve(&diff);
-                self.relative_base += diff;
+            Effect::Output(value) => {
+                self.record(TraceEvent::Output(value.clone()));
+                action = Some(Action::Output(value));
             },
 
-            Instruction::Halt => {
-                action = Some(Action::Halt)
+            Effect::Jump { address, immediate } => {
+                if let (Some(profile), true) = (&mut self.profile, immediate) {
+                    profile.jump(self.instruction_ip, address);
+                }
+
+                self.ip = address;
             },
+
+            Effect::AdjustRelativeBase(relative_base) => self.relative_base = relative_base,
+
+            Effect::Halt => action = Some(Action::Halt),
+
+            Effect::Continue => {}
         }
 
-        action
+        Ok(action)
+    }
+
+    pub fn run(&mut self) -> Action<W> {
+        self.try_run().unwrap_or_else(|error| panic!("{}", error))
     }
 
+    pub fn try_run(&mut self) -> Result<Action<W>, IntcodeError<W>> {
+        let mut count: u64 = 0;
 
-    pub fn run(&mut self) -> Action {
         loop {
-            let action = self.step();
+            if self.budget == Some(0) {
+                return Ok(Action::BudgetExhausted);
+            }
+
+            if count.is_multiple_of(TIME_LIMIT_INTERVAL) && self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Ok(Action::BudgetExhausted);
+            }
+
+            count += 1;
+            let action = self.try_step()?;
 
             if let Some(action) = action {
-                return action;
+                return Ok(action);
             }
         }
     }
 }
+
+#[test]
+fn test_faults() {
+    fn fault(program: &str) -> IntcodeError {
+        let mut machine = Machine::new(&intcode_parser(program).unwrap());
+        match machine.try_run() {
+            Err(error) => error,
+            Ok(_) => panic!("Program did not fault")
+        }
+    }
+
+    assert_eq!(fault("1,0,0,0,42"), IntcodeError::InvalidOpcode { ip: 4, instruction: 42 });
+    assert_eq!(fault("301,0,0,0,99"), IntcodeError::InvalidMode { ip: 0, instruction: 301, mode: 3 });
+    assert_eq!(fault("10001,0,0,0,99"), IntcodeError::WriteToImmediate { ip: 0, instruction: 10001 });
+    assert_eq!(fault("204,-5,99"), IntcodeError::NegativeAddress { ip: 0, instruction: 204, address: -5 });
+    assert_eq!(fault("1105,1,500,99"), IntcodeError::JumpOutOfRange { ip: 0, instruction: 1105, target: 500 });
+}
+
+#[test]
+fn test_fault_rewinds_ip() {
+    let mut machine = Machine::new(&intcode_parser("1101,0,0,0,42").unwrap());
+    assert_eq!(machine.try_run().unwrap_err().ip(), 4);
+    assert_eq!(machine.try_step().unwrap_err().ip(), 4);
+}
+
+#[test]
+fn test_checked_arithmetic() {
+    fn fault(program: &str) -> IntcodeError<i64> {
+        let mut machine = Machine::with_memory(DenseMemory::from(intcode_parser::<i64>(program).unwrap()));
+        machine.set_checked_arithmetic(true);
+        machine.try_run().unwrap_err()
+    }
+
+    assert_eq!(fault("1101,9223372036854775807,1,0,99"), IntcodeError::Overflow {
+        ip: 0, instruction: 1101, lhs: i64::MAX, rhs: 1
+    });
+
+    assert_eq!(fault("1101,0,0,0,1102,4294967296,4294967296,0,99"), IntcodeError::Overflow {
+        ip: 4, instruction: 1102, lhs: 1 << 32, rhs: 1 << 32
+    });
+
+    assert_eq!(fault("109,-9223372036854775808,109,-1,99"), IntcodeError::Overflow {
+        ip: 2, instruction: 109, lhs: i64::MIN, rhs: -1
+    });
+
+    // Relative addresses are checked even without checked arithmetic.
+    let mut machine = Machine::with_memory(DenseMemory::from(intcode_parser::<i64>("109,9223372036854775807,204,1,99").unwrap()));
+    assert_eq!(machine.try_run().unwrap_err(), IntcodeError::Overflow {
+        ip: 2, instruction: 204, lhs: i64::MAX, rhs: 1
+    });
+
+    let mut machine = Machine::with_memory(DenseMemory::from(intcode_parser::<i64>("1102,3037000499,3037000499,0,4,0,99").unwrap()));
+    machine.set_checked_arithmetic(true);
+    assert_eq!(machine.run(), Action::Output(9223372030926249001));
+}
+
+#[test]
+fn test_budget() {
+    // Counts down from 100 in a loop, then outputs 0.
+    let program: IntcodeProgram = intcode_parser("1101,0,100,12,1001,12,-1,12,1005,12,4,104,0,99").unwrap();
+
+    let mut machine = Machine::new(&program);
+    machine.set_budget(Some(50));
+    assert_eq!(machine.run(), Action::BudgetExhausted);
+    assert_eq!(machine.budget(), Some(0));
+    assert_eq!(machine.run(), Action::BudgetExhausted);
+
+    machine.set_budget(Some(1000));
+    assert_eq!(machine.run(), Action::Output(0));
+    assert_eq!(machine.budget(), Some(1000 - 152));
+
+    let mut machine = Machine::new(&program);
+    machine.set_time_limit(Some(Duration::ZERO));
+    assert_eq!(machine.run(), Action::BudgetExhausted);
+
+    machine.set_time_limit(None);
+    assert_eq!(machine.run(), Action::Output(0));
+}
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::ops::Index;
+
+use crate::{IntcodeMemory, IntcodeProgram, Word};
+
+pub trait Memory<W: Word = isize>: Clone + Default {
+    // Whether addresses that were never written cost nothing to hold.
+    const SPARSE: bool;
+
+    fn from_program(program: &IntcodeProgram<W>) -> Self;
+
+    fn read(&self, address: usize) -> W;
+    fn write(&mut self, address: usize, value: W);
+
+    // One past the highest address that has been loaded or written.
+    fn extent(&self) -> usize;
+    fn truncate_extent(&mut self, extent: usize);
+
+    // The populated regions of memory as (start address, values) pairs.
+    fn chunks(&self) -> Vec<(usize, &[W])>;
+}
+
+// Memory as one vector, grown to cover the highest address written. This is
+// what a `Machine` uses unless it is given something else.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DenseMemory<W = isize>(Vec<W>);
+
+impl<W> DenseMemory<W> {
+    pub fn as_slice(&self) -> &[W] {
+        &self.0
+    }
+
+    pub fn into_vec(self) -> IntcodeMemory<W> {
+        self.0
+    }
+}
+
+impl<W> From<IntcodeMemory<W>> for DenseMemory<W> {
+    fn from(memory: IntcodeMemory<W>) -> Self {
+        DenseMemory(memory)
+    }
+}
+
+impl<W> Index<usize> for DenseMemory<W> {
+    type Output = W;
+
+    fn index(&self, address: usize) -> &W {
+        &self.0[address]
+    }
+}
+
+impl<W: Word> Memory<W> for DenseMemory<W> {
+    const SPARSE: bool = false;
+
+    fn from_program(program: &IntcodeProgram<W>) -> Self {
+        DenseMemory(program.clone())
+    }
+
+    #[inline]
+    fn read(&self, address: usize) -> W {
+        self.0.get(address).cloned().unwrap_or_default()
+    }
+
+    #[inline]
+    fn write(&mut self, address: usize, value: W) {
+        if address >= self.0.len() {
+            self.0.resize(address+1, W::default());
+        }
+
+        self.0[address] = value;
+    }
+
+    fn extent(&self) -> usize {
+        self.0.len()
+    }
+
+    fn truncate_extent(&mut self, extent: usize) {
+        self.0.truncate(extent);
+    }
+
+    fn chunks(&self) -> Vec<(usize, &[W])> {
+        vec![(0, &self.0[..])]
+    }
+}
+
+const PAGE_BITS: usize = 10;
+const PAGE_SIZE: usize = 1 << PAGE_BITS;
+
+// Memory allocated in fixed-size pages on first write, so programs that
+// scatter writes across huge addresses only pay for the pages they touch.
+#[derive(Clone, Debug, Default)]
+pub struct PagedMemory<W = isize> {
+    pages: HashMap<usize, Box<[W]>>,
+    extent: usize
+}
+
+impl<W> PagedMemory<W> {
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+impl<W: Word> Memory<W> for PagedMemory<W> {
+    const SPARSE: bool = true;
+
+    fn from_program(program: &IntcodeProgram<W>) -> Self {
+        let mut memory = PagedMemory::default();
+
+        for (address, value) in program.iter().enumerate() {
+            memory.write(address, value.clone());
+        }
+
+        memory.extent = program.len();
+        memory
+    }
+
+    fn read(&self, address: usize) -> W {
+        match self.pages.get(&(address >> PAGE_BITS)) {
+            Some(page) => page[address & (PAGE_SIZE - 1)].clone(),
+            None => W::default()
+        }
+    }
+
+    fn write(&mut self, address: usize, value: W) {
+        if value.is_zero() && !self.pages.contains_key(&(address >> PAGE_BITS)) {
+            self.extent = self.extent.max(address + 1);
+            return;
+        }
+
+        let page = self.pages.entry(address >> PAGE_BITS)
+            .or_insert_with(|| vec![W::default(); PAGE_SIZE].into_boxed_slice());
+
+        page[address & (PAGE_SIZE - 1)] = value;
+        self.extent = self.extent.max(address + 1);
+    }
+
+    fn extent(&self) -> usize {
+        self.extent
+    }
+
+    fn truncate_extent(&mut self, extent: usize) {
+        self.pages.retain(|page, _| page << PAGE_BITS < extent);
+
+        let last_page = extent >> PAGE_BITS;
+        if let Some(page) = self.pages.get_mut(&last_page) {
+            for value in page[extent & (PAGE_SIZE - 1)..].iter_mut() {
+                *value = W::default();
+            }
+        }
+
+        self.extent = extent;
+    }
+
+    fn chunks(&self) -> Vec<(usize, &[W])> {
+        let mut chunks = self.pages.iter()
+            .map(|(page, values)| (page << PAGE_BITS, &values[..]))
+            .collect::<Vec<_>>();
+
+        chunks.sort_by_key(|(start, _)| *start);
+        chunks
+    }
+}
+
+#[test]
+fn test_paged_memory() {
+    use crate::{intcode_parser, Action, Machine};
+
+    // Writes its input to a far away address, then reads it back and outputs it.
+    let program = intcode_parser::<isize>("3,1000000000000,4,1000000000000,99").unwrap();
+
+    let mut machine = Machine::with_memory(PagedMemory::from_program(&program));
+    machine.push_input(42);
+
+    assert_eq!(machine.run(), Action::Output(42));
+    assert_eq!(machine.run(), Action::Halt);
+    assert_eq!(machine.memory().page_count(), 2);
+    assert_eq!(machine.memory().extent(), 1000000000001);
+}
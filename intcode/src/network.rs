@@ -0,0 +1,263 @@
+use std::collections::VecDeque;
+
+use crate::{Action, DenseMemory, Framer, IntcodeError, IntcodeProgram, Machine, Memory, Schema, Word};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Packet<W = isize> {
+    pub source: usize,
+    pub destination: W,
+    pub payload: Vec<W>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NetworkEvent<W = isize> {
+    // A packet sent to an address that is not one of the machines.
+    Packet(Packet<W>),
+    // Every machine that has not halted is waiting for a packet or has used
+    // up its own budget.
+    Idle,
+    Halted
+}
+
+struct Node<W: Word, M: Memory<W>> {
+    machine: Machine<W, M>,
+    queue: VecDeque<W>,
+    output: Framer<W>,
+    // Has been given the empty input and asked for more since, so it only
+    // needs running again once a packet arrives.
+    waiting: bool,
+    halted: bool
+}
+
+// Machines exchanging packets, each output as a destination address followed
+// by `arity` payload values. A machine reading from an empty receive queue
+// gets the empty input once and is then left alone until a packet arrives.
+pub struct Network<W: Word = isize, M: Memory<W> = DenseMemory<W>> {
+    nodes: Vec<Node<W, M>>,
+    empty_input: Option<W>,
+    time_slice: Option<u64>,
+    next: usize,
+    unrouted: VecDeque<Packet<W>>
+}
+
+impl<W: Word, M: Memory<W>> Network<W, M> {
+    // The machine at index `n` has address `n`.
+    pub fn new(machines: Vec<Machine<W, M>>, arity: usize) -> Self {
+        let nodes = machines.into_iter()
+            .map(|machine| Node { machine, queue: VecDeque::new(), output: Framer::new(Schema::fixed(arity + 1)), waiting: false, halted: false })
+            .collect();
+
+        Network {
+            nodes,
+            empty_input: Some(W::from_isize(-1)),
+            time_slice: None,
+            next: 0,
+            unrouted: VecDeque::new()
+        }
+    }
+
+    // Runs `size` copies of `program`, each given its own address as its first input.
+    pub fn boot(program: &IntcodeProgram<W>, size: usize, arity: usize) -> Self {
+        let machines = (0..size)
+            .map(|address| {
+                let mut machine = Machine::with_memory(M::from_program(program));
+                machine.push_input(W::from_usize(address));
+                machine
+            })
+            .collect();
+
+        Network::new(machines, arity)
+    }
+
+    // The value read by a machine with nothing queued, -1 by default. With
+    // None a machine waits for a packet as soon as its queue is empty.
+    pub fn set_empty_input(&mut self, value: Option<W>) {
+        self.empty_input = value;
+    }
+
+    // Limits how many instructions a machine may run before the next one gets
+    // a turn. Without a limit each machine runs until it waits for a packet.
+    pub fn set_time_slice(&mut self, instructions: Option<u64>) {
+        self.time_slice = instructions;
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn machine(&self, address: usize) -> Option<&Machine<W, M>> {
+        self.nodes.get(address).map(|node| &node.machine)
+    }
+
+    // Queues a packet for its destination, or reports it from the next `run`
+    // if no machine has that address.
+    pub fn send(&mut self, packet: Packet<W>) {
+        match packet.destination.to_usize().filter(|address| *address < self.nodes.len()) {
+            Some(address) => {
+                let node = &mut self.nodes[address];
+                node.queue.extend(packet.payload);
+                node.waiting = false;
+            },
+
+            None => self.unrouted.push_back(packet)
+        }
+    }
+
+    pub fn run(&mut self) -> NetworkEvent<W> {
+        self.try_run().unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    // Gives each machine a turn in order until a packet leaves the network
+    // or no machine can make progress.
+    pub fn try_run(&mut self) -> Result<NetworkEvent<W>, IntcodeError<W>> {
+        let mut stalled = 0;
+
+        while stalled < self.nodes.len() {
+            if let Some(packet) = self.unrouted.pop_front() {
+                return Ok(NetworkEvent::Packet(packet));
+            }
+
+            let address = self.next;
+            self.next = (self.next + 1) % self.nodes.len();
+
+            if self.turn(address)? {
+                stalled = 0;
+            } else {
+                stalled += 1;
+            }
+        }
+
+        match self.unrouted.pop_front() {
+            Some(packet) => Ok(NetworkEvent::Packet(packet)),
+            None if self.nodes.iter().all(|node| node.halted) => Ok(NetworkEvent::Halted),
+            None => Ok(NetworkEvent::Idle)
+        }
+    }
+
+    // Runs one machine until it blocks, returning whether it could run at all.
+    // A machine that has used up its own budget or time limit cannot.
+    fn turn(&mut self, address: usize) -> Result<bool, IntcodeError<W>> {
+        let node = &mut self.nodes[address];
+
+        if node.halted || (node.waiting && node.queue.is_empty()) || node.machine.exhausted() {
+            return Ok(false);
+        }
+
+        // The time slice is taken out of the machine's own budget, which is
+        // put back less what the turn used.
+        let budget = node.machine.budget();
+        let slice = self.time_slice.map(|slice| budget.map_or(slice, |budget| budget.min(slice)));
+        if slice.is_some() {
+            node.machine.set_budget(slice);
+        }
+
+        let mut packets = Vec::new();
+
+        let result = loop {
+            match node.machine.try_run() {
+                Ok(Action::Output(value)) => if let Some(record) = node.output.push(value) {
+                    let mut payload = record.into_values();
+                    let destination = payload.remove(0);
+                    packets.push(Packet { source: address, destination, payload });
+                },
+
+                Ok(Action::RequiresInput) => match (node.queue.pop_front(), &self.empty_input) {
+                    (Some(value), _) => {
+                        node.machine.push_input(value);
+                        node.waiting = false;
+                    },
+
+                    (None, Some(empty)) if !node.waiting => {
+                        node.machine.push_input(empty.clone());
+                        node.waiting = true;
+                    },
+
+                    (None, _) => {
+                        node.waiting = true;
+                        break Ok(true);
+                    }
+                },
+
+                Ok(Action::Halt) => {
+                    node.halted = true;
+                    break Ok(true);
+                },
+
+                Ok(Action::BudgetExhausted) => break Ok(true),
+                Err(error) => break Err(error)
+            }
+        };
+
+        if let Some(slice) = slice {
+            let used = slice - node.machine.budget().unwrap_or(0);
+            node.machine.set_budget(budget.map(|budget| budget - used));
+        }
+
+        for packet in packets {
+            self.send(packet);
+        }
+
+        result
+    }
+}
+
+#[test]
+fn test_network() {
+    use crate::assemble;
+
+    // Machine 0 sends 42 to machine 1, the others add their address to
+    // every value they receive and send it on to 255.
+    let program = assemble("
+                IN -> [addr]
+                JT [addr], #relay
+                OUT #1
+                OUT #42
+        wait:   IN -> [value]
+                JT #1, #wait
+        relay:  IN -> [value]
+                EQ [value], #-1 -> [empty]
+                JT [empty], #relay
+                ADD [value], [addr] -> [value]
+                OUT #255
+                OUT [value]
+                JT #1, #relay
+        addr:   .data 0
+        value:  .data 0
+        empty:  .data 0
+    ").unwrap();
+
+    let mut network: Network = Network::boot(&program, 3, 1);
+    assert_eq!(network.run(), NetworkEvent::Packet(Packet { source: 1, destination: 255, payload: vec![43] }));
+    assert_eq!(network.run(), NetworkEvent::Idle);
+
+    network.send(Packet { source: 255, destination: 2, payload: vec![10] });
+    assert_eq!(network.run(), NetworkEvent::Packet(Packet { source: 2, destination: 255, payload: vec![12] }));
+    assert_eq!(network.run(), NetworkEvent::Idle);
+}
+
+#[test]
+fn test_network_budget() {
+    use crate::assemble;
+
+    let spin = assemble("loop: JT #1, #loop").unwrap();
+    let send = assemble("OUT #255\nOUT #7\nHALT").unwrap();
+
+    for time_slice in [None, Some(30)] {
+        let mut spinner = Machine::new(&spin);
+        spinner.set_budget(Some(100));
+
+        let mut network: Network = Network::new(vec![spinner, Machine::new(&send)], 1);
+        network.set_time_slice(time_slice);
+
+        assert_eq!(network.run(), NetworkEvent::Packet(Packet { source: 1, destination: 255, payload: vec![7] }));
+        assert_eq!(network.machine(0).unwrap().budget(), Some(if time_slice.is_some() { 70 } else { 0 }));
+        assert_eq!(network.machine(1).unwrap().budget(), None);
+
+        assert_eq!(network.run(), NetworkEvent::Idle);
+        assert_eq!(network.machine(0).unwrap().budget(), Some(0));
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+
+use crate::{IntcodeProgram, Word};
+
+// Where a program failed to parse. Offsets are in bytes from the start of
+// the input; `position` turns one into a line and column.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    InvalidValue { offset: usize, token: String },
+    MissingValue { offset: usize },
+    MissingComma { offset: usize, token: String },
+}
+
+impl ParseError {
+    pub fn offset(&self) -> usize {
+        match self {
+            ParseError::InvalidValue { offset, .. } => *offset,
+            ParseError::MissingValue { offset } => *offset,
+            ParseError::MissingComma { offset, .. } => *offset,
+        }
+    }
+
+    pub fn token(&self) -> &str {
+        match self {
+            ParseError::InvalidValue { token, .. } => token,
+            ParseError::MissingValue { .. } => ",",
+            ParseError::MissingComma { token, .. } => token,
+        }
+    }
+
+    // The one-based line and column of the error within `input`.
+    pub fn position(&self, input: &str) -> (usize, usize) {
+        let before = &input[..self.offset()];
+        let line_start = before.rfind('\n').map(|newline| newline + 1).unwrap_or(0);
+
+        (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::InvalidValue { offset, token } =>
+                write!(f, "byte {}: invalid value '{}'", offset, token),
+
+            ParseError::MissingValue { offset } =>
+                write!(f, "byte {}: expected a value before ','", offset),
+
+            ParseError::MissingComma { offset, token } =>
+                write!(f, "byte {}: expected ',' before '{}'", offset, token),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Values separated by commas or line breaks. Other whitespace between values
+// is ignored, `#` starts a comment running to the end of the line and a line
+// may end with a comma.
+pub fn intcode_parser<W: Word>(input: &str) -> Result<IntcodeProgram<W>, ParseError> {
+    let mut program = Vec::new();
+    let mut separated = true;
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '#' => while chars.next_if(|(_, c)| *c != '\n').is_some() {},
+
+            ',' if separated => return Err(ParseError::MissingValue { offset }),
+            ',' | '\n' => separated = true,
+
+            c if c.is_whitespace() => {},
+
+            _ => {
+                let mut end = offset + c.len_utf8();
+                while let Some((next, c)) = chars.next_if(|(_, c)| *c != ',' && *c != '#' && !c.is_whitespace()) {
+                    end = next + c.len_utf8();
+                }
+
+                let token = &input[offset..end];
+                if !separated {
+                    return Err(ParseError::MissingComma { offset, token: token.to_string() });
+                }
+
+                match token.parse() {
+                    Ok(value) => program.push(value),
+                    Err(_) => return Err(ParseError::InvalidValue { offset, token: token.to_string() })
+                }
+
+                separated = false;
+            }
+        }
+    }
+
+    Ok(program)
+}
+
+#[test]
+fn test_intcode_parser() {
+    assert_eq!(intcode_parser::<isize>("1,0,0,3,99\n"), Ok(vec![1, 0, 0, 3, 99]));
+    assert_eq!(intcode_parser::<isize>("
+        # Adds two numbers
+        1, 5, 6, 0,   # [0] = [5] + [6]
+        99
+        -2, 3,
+    "), Ok(vec![1, 5, 6, 0, 99, -2, 3]));
+
+    let error = intcode_parser::<isize>("1,2\n3,x4,5").unwrap_err();
+    assert_eq!(error, ParseError::InvalidValue { offset: 6, token: "x4".to_string() });
+    assert_eq!(error.position("1,2\n3,x4,5"), (2, 3));
+
+    assert_eq!(intcode_parser::<isize>("1,,2"), Err(ParseError::MissingValue { offset: 2 }));
+    assert_eq!(intcode_parser::<isize>(",1"), Err(ParseError::MissingValue { offset: 0 }));
+    assert_eq!(intcode_parser::<isize>("1,\n,2"), Err(ParseError::MissingValue { offset: 3 }));
+    assert_eq!(intcode_parser::<isize>("1,2 3"), Err(ParseError::MissingComma { offset: 4, token: "3".to_string() }));
+}
@@ -0,0 +1,229 @@
+use std::collections::VecDeque;
+
+use crate::{Action, DenseMemory, IntcodeError, IntcodeProgram, Machine, Memory, Word};
+
+struct Stage<W: Word, M: Memory<W>> {
+    machine: Machine<W, M>,
+    queue: VecDeque<W>,
+    outputs: Vec<W>,
+    targets: Vec<usize>,
+    halted: bool
+}
+
+// Machines wired output to input. Every output of a stage is copied to each
+// stage it is connected to, so any graph of stages works: chains, rings,
+// fan-out and fan-in. A stage fed by several others reads their values in
+// the order they were produced.
+pub struct Pipeline<W: Word = isize, M: Memory<W> = DenseMemory<W>> {
+    stages: Vec<Stage<W, M>>
+}
+
+impl<W: Word, M: Memory<W>> Default for Pipeline<W, M> {
+    fn default() -> Self {
+        Pipeline { stages: Vec::new() }
+    }
+}
+
+impl<W: Word, M: Memory<W>> Pipeline<W, M> {
+    pub fn new() -> Self {
+        Pipeline::default()
+    }
+
+    // Copies of `program` connected one after the other, each given its
+    // phase setting as its first input.
+    pub fn series(program: &IntcodeProgram<W>, phases: &[W]) -> Self {
+        let mut pipeline = Pipeline::new();
+
+        for phase in phases {
+            let mut machine = Machine::with_memory(M::from_program(program));
+            machine.push_input(phase.clone());
+
+            let stage = pipeline.add_stage(machine);
+            if stage > 0 {
+                pipeline.connect(stage - 1, stage);
+            }
+        }
+
+        pipeline
+    }
+
+    // Like `series`, with the last stage feeding back into the first.
+    pub fn ring(program: &IntcodeProgram<W>, phases: &[W]) -> Self {
+        let mut pipeline = Pipeline::series(program, phases);
+
+        if !phases.is_empty() {
+            pipeline.connect(phases.len() - 1, 0);
+        }
+
+        pipeline
+    }
+
+    pub fn add_stage(&mut self, machine: Machine<W, M>) -> usize {
+        self.stages.push(Stage { machine, queue: VecDeque::new(), outputs: Vec::new(), targets: Vec::new(), halted: false });
+        self.stages.len() - 1
+    }
+
+    pub fn connect(&mut self, from: usize, to: usize) {
+        assert!(to < self.stages.len(), "No stage {} to connect to", to);
+        self.stages[from].targets.push(to);
+    }
+
+    // Queues a value for a stage, after anything its inputs already sent.
+    pub fn push_input(&mut self, stage: usize, value: W) {
+        self.stages[stage].queue.push_back(value);
+    }
+
+    // Gives every stage the same instruction budget.
+    pub fn set_budget(&mut self, instructions: Option<u64>) {
+        for stage in &mut self.stages {
+            stage.machine.set_budget(instructions);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    pub fn machine(&self, stage: usize) -> &Machine<W, M> {
+        &self.stages[stage].machine
+    }
+
+    // Every value a stage has output so far.
+    pub fn outputs(&self, stage: usize) -> &[W] {
+        &self.stages[stage].outputs
+    }
+
+    pub fn run(&mut self) -> bool {
+        self.try_run().unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    // Runs the stages in turn, each until it waits for input, until none of
+    // them can make progress. Returns whether every stage halted, rather than
+    // getting stuck waiting for input or running out of budget.
+    pub fn try_run(&mut self) -> Result<bool, IntcodeError<W>> {
+        loop {
+            let mut progress = false;
+
+            for stage in 0..self.stages.len() {
+                progress |= self.turn(stage)?;
+            }
+
+            if !progress {
+                return Ok(self.stages.iter().all(|stage| stage.halted));
+            }
+        }
+    }
+
+    fn turn(&mut self, index: usize) -> Result<bool, IntcodeError<W>> {
+        let stage = &mut self.stages[index];
+        let mut produced = Vec::new();
+        let mut progress = false;
+        let mut fault = None;
+
+        while !stage.halted {
+            let action = match stage.machine.try_run() {
+                Ok(action) => action,
+                Err(error) => {
+                    fault = Some(error);
+                    break;
+                }
+            };
+
+            match action {
+                Action::Output(value) => produced.push(value),
+
+                Action::RequiresInput => match stage.queue.pop_front() {
+                    Some(value) => stage.machine.push_input(value),
+                    None => break
+                },
+
+                Action::Halt => stage.halted = true,
+                Action::BudgetExhausted => break
+            }
+
+            progress = true;
+        }
+
+        stage.outputs.extend(produced.iter().cloned());
+
+        // Values output before a fault are passed on all the same.
+        for target in stage.targets.clone() {
+            self.stages[target].queue.extend(produced.iter().cloned());
+        }
+
+        match fault {
+            Some(error) => Err(error),
+            None => Ok(progress)
+        }
+    }
+}
+
+#[cfg(test)]
+const AMPLIFIER: &str = "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5";
+
+#[test]
+fn test_series_and_ring() {
+    use crate::intcode_parser;
+
+    let mut chain: Pipeline = Pipeline::series(&intcode_parser("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0").unwrap(), &[4, 3, 2, 1, 0]);
+    chain.push_input(0, 0);
+    assert!(chain.run());
+    assert_eq!(chain.outputs(4), [43210]);
+
+    let mut ring: Pipeline = Pipeline::ring(&intcode_parser(AMPLIFIER).unwrap(), &[9, 8, 7, 6, 5]);
+    ring.push_input(0, 0);
+    assert!(ring.run());
+    assert_eq!(ring.outputs(4).last(), Some(&139629729));
+}
+
+#[test]
+fn test_fan_out_and_in() {
+    use crate::intcode_parser;
+
+    let program = |source: &str| Machine::new(&intcode_parser(source).unwrap());
+
+    // Doubles a value, then adds one and triples it in two branches that
+    // are summed at the end.
+    let mut pipeline = Pipeline::new();
+    let double = pipeline.add_stage(program("3,9,102,2,9,9,4,9,99,0"));
+    let increment = pipeline.add_stage(program("3,9,101,1,9,9,4,9,99,0"));
+    let triple = pipeline.add_stage(program("3,9,102,3,9,9,4,9,99,0"));
+    let sum = pipeline.add_stage(program("3,11,3,12,1,11,12,11,4,11,99,0,0"));
+
+    pipeline.connect(double, increment);
+    pipeline.connect(double, triple);
+    pipeline.connect(increment, sum);
+    pipeline.connect(triple, sum);
+
+    pipeline.push_input(double, 5);
+    assert!(pipeline.run());
+    assert_eq!(pipeline.outputs(sum), [11 + 30]);
+
+    // A stage left waiting for input means the pipeline did not complete.
+    let mut stuck: Pipeline = Pipeline::new();
+    stuck.add_stage(program("3,9,102,2,9,9,4,9,99,0"));
+    assert!(!stuck.run());
+}
+
+#[test]
+fn test_fault_keeps_outputs() {
+    use crate::intcode_parser;
+
+    let program = |source: &str| Machine::new(&intcode_parser(source).unwrap());
+
+    // The second stage outputs 7, then jumps out of the program.
+    let mut pipeline = Pipeline::new();
+    let echo = pipeline.add_stage(program("3,5,4,5,99,0"));
+    let faulty = pipeline.add_stage(program("104,7,1105,1,-1"));
+    pipeline.connect(faulty, echo);
+
+    assert!(pipeline.try_run().is_err());
+    assert_eq!(pipeline.outputs(faulty), [7]);
+
+    assert!(pipeline.try_run().is_err());
+    assert_eq!(pipeline.outputs(echo), [7]);
+}
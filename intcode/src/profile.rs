@@ -0,0 +1,184 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use crate::{disassemble, IntcodeProgram, Line, Machine, Memory, Word};
+
+const REPORT_ROWS: usize = 10;
+
+// A taken backward jump to a constant address, covering the code from
+// `start` up to and including the jump instruction at `end`. Jumps through
+// memory are left out as they are usually returns from subroutines.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Loop {
+    pub start: usize,
+    pub end: usize,
+    pub iterations: u64,
+    pub executions: u64
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    executions: HashMap<usize, u64>,
+    opcodes: BTreeMap<&'static str, u64>,
+    reads: HashMap<usize, u64>,
+    writes: HashMap<usize, u64>,
+    jumps: HashMap<(usize, usize), u64>,
+    total: u64
+}
+
+impl Profile {
+    pub(crate) fn execute(&mut self, ip: usize, mnemonic: &'static str) {
+        *self.executions.entry(ip).or_default() += 1;
+        *self.opcodes.entry(mnemonic).or_default() += 1;
+        self.total += 1;
+    }
+
+    pub(crate) fn read(&mut self, address: usize) {
+        *self.reads.entry(address).or_default() += 1;
+    }
+
+    pub(crate) fn write(&mut self, address: usize) {
+        *self.writes.entry(address).or_default() += 1;
+    }
+
+    pub(crate) fn jump(&mut self, from: usize, to: usize) {
+        if to <= from {
+            *self.jumps.entry((from, to)).or_default() += 1;
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn executions(&self, address: usize) -> u64 {
+        self.executions.get(&address).copied().unwrap_or(0)
+    }
+
+    pub fn reads(&self, address: usize) -> u64 {
+        self.reads.get(&address).copied().unwrap_or(0)
+    }
+
+    pub fn writes(&self, address: usize) -> u64 {
+        self.writes.get(&address).copied().unwrap_or(0)
+    }
+
+    // Execution counts per mnemonic, most frequent first.
+    pub fn opcodes(&self) -> Vec<(&'static str, u64)> {
+        let mut opcodes = self.opcodes.iter()
+            .map(|(mnemonic, count)| (*mnemonic, *count))
+            .collect::<Vec<_>>();
+
+        opcodes.sort_by_key(|(_, count)| Reverse(*count));
+        opcodes
+    }
+
+    // Addresses by total reads and writes, hottest first.
+    pub fn hot_memory(&self) -> Vec<(usize, u64, u64)> {
+        let mut addresses = self.reads.keys().chain(self.writes.keys()).copied().collect::<Vec<_>>();
+        addresses.sort_unstable();
+        addresses.dedup();
+
+        let mut memory = addresses.into_iter()
+            .map(|address| (address, self.reads(address), self.writes(address)))
+            .collect::<Vec<_>>();
+
+        memory.sort_by(|a, b| (b.1 + b.2).cmp(&(a.1 + a.2)).then(a.0.cmp(&b.0)));
+        memory
+    }
+
+    // Loops by the number of instructions executed inside them, hottest first.
+    pub fn hot_loops(&self) -> Vec<Loop> {
+        let mut loops = self.jumps.iter()
+            .map(|(&(end, start), &iterations)| Loop {
+                start,
+                end,
+                iterations,
+                executions: (start..=end).map(|address| self.executions(address)).sum()
+            })
+            .collect::<Vec<_>>();
+
+        loops.sort_by(|a, b| b.executions.cmp(&a.executions).then(a.start.cmp(&b.start)));
+        loops
+    }
+
+    // A summary of the hottest opcodes, loops and memory, followed by the
+    // disassembly of `program` with execution counts in the left margin.
+    pub fn report<W: Word>(&self, program: &IntcodeProgram<W>) -> String {
+        let mut report = String::new();
+        let percent = |count: u64| count as f64 * 100.0 / self.total.max(1) as f64;
+
+        writeln!(report, "Executed {} instructions", self.total).unwrap();
+
+        writeln!(report, "\nOpcodes:").unwrap();
+        for (mnemonic, count) in self.opcodes() {
+            writeln!(report, "  {:<6} {:>12} {:>6.2}%", mnemonic, count, percent(count)).unwrap();
+        }
+
+        writeln!(report, "\nHottest loops:").unwrap();
+        for hot in self.hot_loops().iter().take(REPORT_ROWS) {
+            writeln!(report, "  {:04}..{:04} {:>12} iterations {:>12} instructions {:>6.2}%",
+                hot.start, hot.end, hot.iterations, hot.executions, percent(hot.executions)).unwrap();
+        }
+
+        writeln!(report, "\nHottest memory:").unwrap();
+        for (address, reads, writes) in self.hot_memory().into_iter().take(REPORT_ROWS) {
+            writeln!(report, "  {:04} {:>12} reads {:>12} writes", address, reads, writes).unwrap();
+        }
+
+        writeln!(report, "\nListing:").unwrap();
+        for line in disassemble(program) {
+            match &line {
+                Line::Instruction(address, _) => writeln!(report, "{:>12}  {}", self.executions(*address), line).unwrap(),
+                Line::Data(..) => writeln!(report, "{:>12}  {}", "", line).unwrap(),
+            }
+        }
+
+        report
+    }
+}
+
+impl<W: Word, M: Memory<W>> Machine<W, M> {
+    pub fn start_profile(&mut self) {
+        self.profile = Some(Profile::default());
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    pub fn take_profile(&mut self) -> Option<Profile> {
+        self.profile.take()
+    }
+}
+
+#[test]
+fn test_profile() {
+    use crate::{assemble, Action};
+
+    let program = assemble("
+        loop:   ADD [count], #1 -> [count]
+                LT [count], #5 -> [more]
+                JT [more], #loop
+                OUT [count]
+                HALT
+        count:  .data 0
+        more:   .data 0
+    ").unwrap();
+
+    let mut machine = Machine::new(&program);
+    machine.start_profile();
+    assert_eq!(machine.run(), Action::Output(5));
+
+    let profile = machine.take_profile().unwrap();
+    assert_eq!(profile.total(), 16);
+    assert_eq!(profile.executions(0), 5);
+    assert_eq!(profile.executions(11), 1);
+    assert_eq!(profile.opcodes()[0], ("ADD", 5));
+    assert_eq!(profile.reads(14), 11);
+    assert_eq!(profile.writes(14), 5);
+
+    assert_eq!(profile.hot_loops(), vec![Loop { start: 0, end: 8, iterations: 4, executions: 15 }]);
+    assert!(profile.report(&program).contains("           5  0000: ADD [14], #1 -> [14]"));
+}
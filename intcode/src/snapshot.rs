@@ -0,0 +1,265 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use crate::{DenseMemory, Machine, Memory, Word};
+
+const SAVE_MAGIC: &str = "intcode-save";
+const SAVE_VERSION: u32 = 2;
+
+// How far past the values it lists a save may ask dense memory to reach, so
+// a corrupt extent or chunk start is an error rather than a huge allocation.
+const MAX_SAVE_GAP: usize = 1 << 20;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot<W = isize, M = DenseMemory<W>> {
+    pub memory: M,
+    pub ip: usize,
+    pub relative_base: W,
+    pub inputs: Vec<W>
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    NotASaveFile,
+    UnsupportedVersion(u32),
+    Malformed { line: usize, message: String }
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "{}", error),
+            SaveError::NotASaveFile => write!(f, "not an intcode save file"),
+            SaveError::UnsupportedVersion(version) => write!(f, "unsupported save version {}", version),
+            SaveError::Malformed { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+
+fn join<W: Word>(values: &[W]) -> String {
+    values.iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl<W: Word, M: Memory<W>> Snapshot<W, M> {
+    // The save format is line based: a `intcode-save <version>` header
+    // followed by one `<field> <value>` line per field. Version 1 stored
+    // memory as a single dense `memory` line; version 2 has an `extent` and
+    // a `memory <start> <values>` line for each populated chunk.
+    pub fn save<Wr: Write>(&self, writer: &mut Wr) -> io::Result<()> {
+        writeln!(writer, "{} {}", SAVE_MAGIC, SAVE_VERSION)?;
+        writeln!(writer, "ip {}", self.ip)?;
+        writeln!(writer, "relative_base {}", self.relative_base)?;
+        writeln!(writer, "inputs {}", join(&self.inputs))?;
+        writeln!(writer, "extent {}", self.memory.extent())?;
+
+        for (start, values) in self.memory.chunks() {
+            writeln!(writer, "memory {} {}", start, join(values))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn load<R: BufRead>(reader: R) -> Result<Snapshot<W, M>, SaveError> {
+        let mut lines = reader.lines();
+
+        let header = lines.next().ok_or(SaveError::NotASaveFile)??;
+        let version = match header.split_once(' ') {
+            Some((SAVE_MAGIC, version)) => version.trim().parse::<u32>().map_err(|_| SaveError::NotASaveFile)?,
+            _ => return Err(SaveError::NotASaveFile)
+        };
+
+        if version == 0 || version > SAVE_VERSION {
+            return Err(SaveError::UnsupportedVersion(version));
+        }
+
+        let mut ip = None;
+        let mut relative_base = None;
+        let mut inputs = None;
+        let mut extent = None;
+        let mut loaded = 0;
+        let mut memory = M::default();
+
+        for (index, line) in lines.enumerate() {
+            let line_number = index + 2;
+            let line = line?;
+            let malformed = |message: &str| SaveError::Malformed { line: line_number, message: message.to_string() };
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (field, value) = line.split_once(' ').unwrap_or((&line, ""));
+            let value = value.trim();
+
+            let list = |value: &str| -> Result<Vec<W>, SaveError> {
+                if value.is_empty() {
+                    return Ok(Vec::new());
+                }
+
+                value.split(',')
+                    .map(|word| word.parse::<W>().map_err(|_| malformed(&format!("invalid value '{}'", word))))
+                    .collect()
+            };
+
+            match field {
+                "ip" => ip = Some(value.parse::<usize>().map_err(|_| malformed("invalid ip"))?),
+                "relative_base" => relative_base = Some(value.parse::<W>().map_err(|_| malformed("invalid relative base"))?),
+                "inputs" => inputs = Some(list(value)?),
+                "extent" if version >= 2 => extent = Some((line_number, value.parse::<usize>().map_err(|_| malformed("invalid extent"))?)),
+
+                "memory" => {
+                    let (start, values) = if version >= 2 {
+                        let (start, values) = value.split_once(' ').unwrap_or((value, ""));
+                        (start.parse::<usize>().map_err(|_| malformed("invalid chunk start"))?, values)
+                    } else {
+                        (0, value)
+                    };
+
+                    let values = list(values)?;
+                    if version == 1 {
+                        extent = Some((line_number, values.len()));
+                    }
+
+                    if !M::SPARSE && start > loaded + MAX_SAVE_GAP {
+                        return Err(malformed("chunk start is beyond the saved memory"));
+                    }
+
+                    if start.checked_add(values.len()).is_none() {
+                        return Err(malformed("chunk runs past the end of memory"));
+                    }
+
+                    loaded += values.len();
+
+                    for (offset, value) in values.into_iter().enumerate() {
+                        memory.write(start + offset, value);
+                    }
+                },
+
+                _ => return Err(malformed(&format!("unknown field '{}'", field)))
+            }
+        }
+
+        let missing = |field: &str| SaveError::Malformed { line: 0, message: format!("missing field '{}'", field) };
+
+        let (extent_line, extent) = extent.ok_or_else(|| missing("extent"))?;
+        if !M::SPARSE && extent > loaded + MAX_SAVE_GAP {
+            return Err(SaveError::Malformed { line: extent_line, message: format!("extent {} is beyond the saved memory", extent) });
+        }
+
+        if memory.extent() > extent {
+            memory.truncate_extent(extent);
+        } else if memory.extent() < extent {
+            memory.write(extent - 1, W::default());
+        }
+
+        Ok(Snapshot {
+            ip: ip.ok_or_else(|| missing("ip"))?,
+            relative_base: relative_base.ok_or_else(|| missing("relative_base"))?,
+            inputs: inputs.ok_or_else(|| missing("inputs"))?,
+            memory
+        })
+    }
+}
+
+impl<W: Word, M: Memory<W>> Machine<W, M> {
+    pub fn from_snapshot(snapshot: &Snapshot<W, M>) -> Self {
+        let mut machine = Machine::with_memory(snapshot.memory.clone());
+        machine.restore(snapshot);
+        machine
+    }
+
+    pub fn snapshot(&self) -> Snapshot<W, M> {
+        Snapshot {
+            memory: self.memory.clone(),
+            ip: self.ip,
+            relative_base: self.relative_base.clone(),
+            inputs: self.inputs.clone()
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot<W, M>) {
+        self.memory.clone_from(&snapshot.memory);
+        self.ip = snapshot.ip;
+        self.instruction_ip = snapshot.ip;
+        self.relative_base = snapshot.relative_base.clone();
+        self.inputs.clone_from(&snapshot.inputs);
+        self.cache.clear();
+
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+    }
+}
+
+#[test]
+fn test_snapshot_restore() {
+    use crate::{intcode_parser, Action};
+
+    let mut machine = Machine::new(&intcode_parser("3,9,4,9,1001,9,1,9,99,0").unwrap());
+    machine.push_input(5);
+
+    let snapshot = machine.snapshot();
+    assert_eq!(machine.run(), Action::Output(5));
+
+    let mut fork = machine.clone();
+    assert_eq!(fork.run(), Action::Halt);
+    assert_eq!(fork.memory()[9], 6);
+    assert_eq!(machine.memory()[9], 5);
+
+    machine.restore(&snapshot);
+    assert_eq!(machine.run(), Action::Output(5));
+}
+
+#[test]
+fn test_save_load() {
+    let mut machine = Machine::new(&crate::intcode_parser("3,9,4,9,109,-4,3,9,99,0").unwrap());
+    machine.push_input(-7);
+    machine.push_input(3);
+    machine.run();
+    machine.run();
+
+    let mut saved = Vec::new();
+    machine.snapshot().save(&mut saved).unwrap();
+
+    assert_eq!(Snapshot::load(&saved[..]).unwrap(), machine.snapshot());
+    assert!(matches!(Snapshot::<isize>::load(&b"intcode-save 99\n"[..]), Err(SaveError::UnsupportedVersion(99))));
+    assert!(matches!(Snapshot::<isize>::load(&b"1,2,3\n"[..]), Err(SaveError::NotASaveFile)));
+
+    let huge_extent = "intcode-save 2\nip 0\nrelative_base 0\ninputs\nextent 18446744073709551615\nmemory 0 99\n";
+    assert!(matches!(Snapshot::<isize>::load(huge_extent.as_bytes()), Err(SaveError::Malformed { line: 5, .. })));
+
+    let huge_start = "intcode-save 2\nip 0\nrelative_base 0\ninputs\nextent 1\nmemory 1000000000000 99\n";
+    assert!(matches!(Snapshot::<isize>::load(huge_start.as_bytes()), Err(SaveError::Malformed { line: 6, .. })));
+
+    let version_1 = "intcode-save 1\nip 2\nrelative_base 0\ninputs 4\nmemory 3,0,99\n";
+    assert_eq!(Snapshot::<isize>::load(version_1.as_bytes()).unwrap(), Snapshot {
+        memory: DenseMemory::from(vec![3, 0, 99]),
+        ip: 2,
+        relative_base: 0,
+        inputs: vec![4]
+    });
+
+    let mut paged = Machine::with_memory(crate::PagedMemory::from_program(&vec![3isize, 1 << 40, 99]));
+    paged.push_input(5);
+    paged.run();
+
+    let mut saved = Vec::new();
+    paged.snapshot().save(&mut saved).unwrap();
+
+    let loaded = Snapshot::<isize, crate::PagedMemory>::load(&saved[..]).unwrap();
+    assert_eq!(loaded.memory.read(1 << 40), 5);
+    assert_eq!(loaded.memory.extent(), (1 << 40) + 1);
+    assert_eq!(loaded.memory.page_count(), 2);
+}
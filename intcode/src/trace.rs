@@ -0,0 +1,229 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::encoding::{invalid_data, read_varint, write_varint};
+use crate::{DenseMemory, IntcodeError, IntcodeProgram, Machine, Memory, Parameter, Word};
+
+const TRACE_MAGIC: &[u8; 4] = b"ICTR";
+const TRACE_VERSION: u8 = 1;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TraceEvent<W = isize> {
+    Execute { ip: usize, instruction: W },
+    Write { address: usize, value: W },
+    Poke { address: usize, value: W },
+    Input(W),
+    Output(W)
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Trace<W = isize> {
+    pub events: Vec<TraceEvent<W>>
+}
+
+impl<W: Word> Trace<W> {
+    pub fn new() -> Self {
+        Trace { events: Vec::new() }
+    }
+
+    // Each event is a one byte tag followed by its fields as varints, with
+    // signed values zigzag encoded.
+    pub fn write<Wr: Write>(&self, writer: &mut Wr) -> io::Result<()> {
+        writer.write_all(TRACE_MAGIC)?;
+        writer.write_all(&[TRACE_VERSION])?;
+
+        for event in &self.events {
+            match event {
+                TraceEvent::Execute { ip, instruction } => {
+                    writer.write_all(&[0])?;
+                    write_varint(writer, *ip as u128)?;
+                    instruction.encode(writer)?;
+                },
+
+                TraceEvent::Write { address, value } => {
+                    writer.write_all(&[1])?;
+                    write_varint(writer, *address as u128)?;
+                    value.encode(writer)?;
+                },
+
+                TraceEvent::Poke { address, value } => {
+                    writer.write_all(&[2])?;
+                    write_varint(writer, *address as u128)?;
+                    value.encode(writer)?;
+                },
+
+                TraceEvent::Input(value) => {
+                    writer.write_all(&[3])?;
+                    value.encode(writer)?;
+                },
+
+                TraceEvent::Output(value) => {
+                    writer.write_all(&[4])?;
+                    value.encode(writer)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Trace<W>> {
+        let mut header = [0; 5];
+        reader.read_exact(&mut header)?;
+
+        if &header[..4] != TRACE_MAGIC {
+            return Err(invalid_data("not an intcode trace file"));
+        }
+
+        if header[4] != TRACE_VERSION {
+            return Err(invalid_data(&format!("unsupported trace version {}", header[4])));
+        }
+
+        let mut events = Vec::new();
+
+        loop {
+            let mut tag = [0];
+            if reader.read(&mut tag)? == 0 {
+                return Ok(Trace { events });
+            }
+
+            let event = match tag[0] {
+                0 => TraceEvent::Execute { ip: read_varint(reader)? as usize, instruction: W::decode(reader)? },
+                1 => TraceEvent::Write { address: read_varint(reader)? as usize, value: W::decode(reader)? },
+                2 => TraceEvent::Poke { address: read_varint(reader)? as usize, value: W::decode(reader)? },
+                3 => TraceEvent::Input(W::decode(reader)?),
+                4 => TraceEvent::Output(W::decode(reader)?),
+                tag => return Err(invalid_data(&format!("unknown trace event {}", tag)))
+            };
+
+            events.push(event);
+        }
+    }
+}
+
+impl<W: Word, M: Memory<W>> Machine<W, M> {
+    pub fn start_trace(&mut self) {
+        self.trace = Some(Trace::new());
+    }
+
+    pub fn trace(&self) -> Option<&Trace<W>> {
+        self.trace.as_ref()
+    }
+
+    pub fn take_trace(&mut self) -> Option<Trace<W>> {
+        self.trace.take()
+    }
+
+    pub(crate) fn record(&mut self, event: TraceEvent<W>) {
+        self.remember(&event);
+
+        if let Some(trace) = &mut self.trace {
+            trace.events.push(event);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Divergence<W = isize> {
+    pub event: usize,
+    pub expected: Option<TraceEvent<W>>,
+    pub actual: Option<TraceEvent<W>>,
+    pub fault: Option<IntcodeError<W>>
+}
+
+impl<W: Word> fmt::Display for Divergence<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "diverged at event {}: expected {:?}, got {:?}", self.event, self.expected, self.actual)?;
+
+        if let Some(fault) = &self.fault {
+            write!(f, " ({})", fault)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Re-runs `program` one instruction at a time, feeding it the inputs and
+// pokes recorded in `trace`, and checks each step produces the same events.
+pub fn replay<W: Word>(program: &IntcodeProgram<W>, trace: &Trace<W>) -> Result<(), Divergence<W>> {
+    let events = &trace.events;
+
+    let mut machine = Machine::with_memory(DenseMemory::from_program(program));
+    machine.start_trace();
+
+    let mut index = 0;
+    while index < events.len() {
+        let end = match events[index] {
+            TraceEvent::Poke { .. } => index + 1,
+            _ => events[index+1..].iter()
+                .position(|event| matches!(event, TraceEvent::Execute { .. } | TraceEvent::Poke { .. }))
+                .map(|offset| index + 1 + offset)
+                .unwrap_or(events.len())
+        };
+
+        let expected = &events[index..end];
+        let mut fault = None;
+
+        match &events[index] {
+            TraceEvent::Poke { address, value } => machine.write(value.clone(), &Parameter::Position(*address)),
+
+            _ => {
+                for event in expected {
+                    if let TraceEvent::Input(value) = event {
+                        machine.push_input(value.clone());
+                    }
+                }
+
+                fault = machine.try_step().err();
+            }
+        }
+
+        let actual = machine.trace.as_mut()
+            .map(|trace| trace.events.drain(..).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        for offset in 0..expected.len().max(actual.len()) {
+            if expected.get(offset) != actual.get(offset) {
+                return Err(Divergence {
+                    event: index + offset,
+                    expected: expected.get(offset).cloned(),
+                    actual: actual.get(offset).cloned(),
+                    fault
+                });
+            }
+        }
+
+        index = end;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_trace_replay() {
+    let program: IntcodeProgram = crate::intcode_parser(include_str!("../../input/2019/day9.txt")).unwrap();
+
+    let mut machine = Machine::new(&program);
+    machine.start_trace();
+    machine.write(1, &Parameter::Position(1000));
+    machine.push_input(1);
+    while machine.run() != crate::Action::Halt {}
+
+    let mut encoded = Vec::new();
+    machine.trace().unwrap().write(&mut encoded).unwrap();
+    let trace = Trace::read(&mut &encoded[..]).unwrap();
+
+    assert_eq!(&trace, machine.trace().unwrap());
+    assert_eq!(replay(&program, &trace), Ok(()));
+
+    // 0004: LT [63], #34463338 -> [63]
+    let mut patched = program.clone();
+    patched[6] = isize::MAX;
+
+    assert_eq!(replay(&patched, &trace), Err(Divergence {
+        event: 4,
+        expected: Some(TraceEvent::Write { address: 63, value: 0 }),
+        actual: Some(TraceEvent::Write { address: 63, value: 1 }),
+        fault: None
+    }));
+}
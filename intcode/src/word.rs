@@ -0,0 +1,227 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+
+use crate::encoding::{invalid_data, read_varint, unzigzag, write_varint, zigzag};
+
+// The integer type stored in each memory cell. `add` and `mul` behave like
+// the underlying type's operators, so fixed-width words overflow the same way
+// `+` and `*` do; the checked variants return None instead.
+pub trait Word: Clone + Default + fmt::Debug + fmt::Display + FromStr + PartialEq + PartialOrd {
+    fn from_isize(value: isize) -> Self;
+    fn from_usize(value: usize) -> Self;
+
+    fn to_isize(&self) -> Option<isize>;
+    fn to_usize(&self) -> Option<usize>;
+
+    fn add(&self, other: &Self) -> Self;
+    fn mul(&self, other: &Self) -> Self;
+
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+
+    fn is_zero(&self) -> bool;
+    fn is_negative(&self) -> bool;
+
+    // Zigzag varint encoding, used by the trace and binary program formats.
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self>;
+}
+
+macro_rules! primitive_word {
+    ($($word:ty),*) => {
+        $(
+            impl Word for $word {
+                fn from_isize(value: isize) -> Self {
+                    value as $word
+                }
+
+                fn from_usize(value: usize) -> Self {
+                    value as $word
+                }
+
+                fn to_isize(&self) -> Option<isize> {
+                    isize::try_from(*self).ok()
+                }
+
+                fn to_usize(&self) -> Option<usize> {
+                    usize::try_from(*self).ok()
+                }
+
+                fn add(&self, other: &Self) -> Self {
+                    *self + *other
+                }
+
+                fn mul(&self, other: &Self) -> Self {
+                    *self * *other
+                }
+
+                fn checked_add(&self, other: &Self) -> Option<Self> {
+                    <$word>::checked_add(*self, *other)
+                }
+
+                fn checked_mul(&self, other: &Self) -> Option<Self> {
+                    <$word>::checked_mul(*self, *other)
+                }
+
+                fn is_zero(&self) -> bool {
+                    *self == 0
+                }
+
+                fn is_negative(&self) -> bool {
+                    *self < 0
+                }
+
+                fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                    write_varint(writer, zigzag(*self as i128))
+                }
+
+                fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+                    <$word>::try_from(unzigzag(read_varint(reader)?))
+                        .map_err(|_| invalid_data("value does not fit in the word type"))
+                }
+            }
+        )*
+    }
+}
+
+primitive_word!(isize, i64, i128);
+
+#[cfg(feature = "bigint")]
+mod bigint {
+    use std::io::{self, Read, Write};
+
+    use num_bigint::{BigInt, BigUint, Sign};
+    use num_traits::{ToPrimitive, Zero};
+
+    use super::Word;
+    use crate::encoding::invalid_data;
+
+    impl Word for BigInt {
+        fn from_isize(value: isize) -> Self {
+            BigInt::from(value)
+        }
+
+        fn from_usize(value: usize) -> Self {
+            BigInt::from(value)
+        }
+
+        fn to_isize(&self) -> Option<isize> {
+            ToPrimitive::to_isize(self)
+        }
+
+        fn to_usize(&self) -> Option<usize> {
+            ToPrimitive::to_usize(self)
+        }
+
+        fn add(&self, other: &Self) -> Self {
+            self + other
+        }
+
+        fn mul(&self, other: &Self) -> Self {
+            self * other
+        }
+
+        fn checked_add(&self, other: &Self) -> Option<Self> {
+            Some(self + other)
+        }
+
+        fn checked_mul(&self, other: &Self) -> Option<Self> {
+            Some(self * other)
+        }
+
+        fn is_zero(&self) -> bool {
+            Zero::is_zero(self)
+        }
+
+        fn is_negative(&self) -> bool {
+            self.sign() == Sign::Minus
+        }
+
+        fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+            let magnitude = self.magnitude() << 1u32;
+            let mut value = if Word::is_negative(self) { magnitude - 1u32 } else { magnitude };
+
+            loop {
+                let byte = (&value & BigUint::from(0x7fu32)).to_u8().unwrap_or(0);
+                value >>= 7u32;
+
+                if Zero::is_zero(&value) {
+                    return writer.write_all(&[byte]);
+                }
+
+                writer.write_all(&[byte | 0x80])?;
+            }
+        }
+
+        fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+            let mut value = BigUint::zero();
+            let mut shift = 0u32;
+
+            loop {
+                let mut byte = [0];
+                reader.read_exact(&mut byte)?;
+
+                value |= BigUint::from(byte[0] & 0x7f) << shift;
+
+                if byte[0] & 0x80 == 0 {
+                    break;
+                }
+
+                shift = shift.checked_add(7).ok_or_else(|| invalid_data("varint is too long"))?;
+            }
+
+            let negative = value.bit(0);
+            let magnitude = BigInt::from(value >> 1u32);
+
+            Ok(if negative { -magnitude - 1 } else { magnitude })
+        }
+    }
+}
+
+#[cfg(test)]
+fn run_to_halt<W: Word>(program: &str, input: W) -> Vec<W> {
+    use crate::{intcode_parser, Action, DenseMemory, Machine};
+
+    let mut machine = Machine::with_memory(DenseMemory::from(intcode_parser::<W>(program).unwrap()));
+    machine.push_input(input);
+
+    let mut outputs = Vec::new();
+    while let Action::Output(value) = machine.run() {
+        outputs.push(value);
+    }
+
+    outputs
+}
+
+#[test]
+fn test_boost_self_test() {
+    let program = include_str!("../../input/2019/day9.txt");
+
+    assert_eq!(run_to_halt::<i64>(program, 1), vec![3460311188]);
+    assert_eq!(run_to_halt::<i128>(program, 1), vec![3460311188]);
+
+    // 10^12 * 10^12 overflows 64 bits.
+    let square = "1102,1000000000000,1000000000000,7,4,7,99,0";
+    assert_eq!(run_to_halt::<i128>(square, 0), vec![1_000_000_000_000_000_000_000_000]);
+}
+
+#[cfg(feature = "bigint")]
+#[test]
+fn test_bigint_words() {
+    use num_bigint::BigInt;
+
+    let program = include_str!("../../input/2019/day9.txt");
+    assert_eq!(run_to_halt(program, BigInt::from(1)), vec![BigInt::from(3460311188i64)]);
+
+    let huge = "1102,100000000000000000000,100000000000000000000,7,4,7,99,0";
+    let expected = "10000000000000000000000000000000000000000".parse::<BigInt>().unwrap();
+    assert_eq!(run_to_halt(huge, BigInt::from(0)), vec![expected.clone()]);
+
+    for value in [expected.clone(), -expected, BigInt::from(-1), BigInt::from(0)].iter() {
+        let mut encoded = Vec::new();
+        value.encode(&mut encoded).unwrap();
+        assert_eq!(&BigInt::decode(&mut &encoded[..]).unwrap(), value);
+    }
+}
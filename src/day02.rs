@@ -1,68 +1,49 @@
+use intcode::*;
+
+// Instructions a single noun/verb candidate may execute before it is skipped.
+const CANDIDATE_BUDGET: u64 = 10_000;
 
 #[aoc_generator(day2)]
-pub fn intcode_parser(input: &str) -> Vec<usize> {
-  input
-    .split(",")
-    .map(|s| s.parse::<usize>().unwrap())
-    .collect::<Vec<usize>>()
+pub fn day2_generator(input: &str) -> Result<IntcodeProgram, ParseError> {
+  intcode_parser(input)
 }
 
-pub fn execute_intcode(memory: &mut Vec<usize>) {
-  let mut ip = 0;
-
-  loop {
-    let opcode = memory[ip];
-    match opcode {
-      1 => {
-        let lhs_address = memory[ip+1];
-        let rhs_address = memory[ip+2];
-        let output_address = memory[ip+3];
-
-        memory[output_address] = memory[lhs_address] + memory[rhs_address];
-        ip += 4;
-      },
-
-      2 => {
-        let lhs_address = memory[ip+1];
-        let rhs_address = memory[ip+2];
-        let output_address = memory[ip+3];
-
-        memory[output_address] = memory[lhs_address] * memory[rhs_address];
-        ip += 4;
-      },
-
-      99 => return,
-      _ => panic!(format!("Unknown opcode {} at IP {}", opcode, ip))
-    }
+// Runs the program to completion and returns its final memory, or None if
+// it faults or does not halt within the budget.
+pub fn execute_intcode(memory: &IntcodeMemory) -> Option<IntcodeMemory> {
+  let mut machine = Machine::new(memory);
+  machine.set_budget(Some(CANDIDATE_BUDGET));
 
+  match machine.try_run() {
+    Ok(Action::Halt) => Some(machine.memory().as_slice().to_vec()),
+    _ => None
   }
 }
 
 #[aoc(day2, part1)]
-pub fn check_1202(program: &Vec<usize>) -> usize {
+pub fn check_1202(program: &IntcodeProgram) -> isize {
   let mut memory = program.clone();
 
   memory[1] = 12;
   memory[2] = 2;
 
-  execute_intcode(&mut memory);
+  let memory = execute_intcode(&memory).expect("Program did not halt");
 
   return memory[0];
 }
 
 
 #[aoc(day2, part2)]
-pub fn execute_intcode_part2(program: &Vec<usize>) -> usize {
+pub fn execute_intcode_part2(program: &IntcodeProgram) -> isize {
   for noun in 0..99 {
     for verb in 0..99 {
       let mut memory = program.clone();
       memory[1] = noun;
       memory[2] = verb;
 
-      execute_intcode(&mut memory);
-
-      if memory[0] == 19690720 {
-        return 100 * noun + verb;
+      match execute_intcode(&memory) {
+        Some(memory) if memory[0] == 19690720 => return 100 * noun + verb,
+        _ => {}
       }
     }
   }
@@ -72,14 +53,15 @@ pub fn execute_intcode_part2(program: &Vec<usize>) -> usize {
 
 #[test]
 pub fn tests() {
-  pub fn execute(input: &str) -> Vec<usize> {
-    let mut program = intcode_parser(input);
-    execute_intcode(&mut program);
-    program
+  pub fn execute(input: &str) -> Option<IntcodeMemory> {
+    execute_intcode(&intcode_parser(input).unwrap())
   }
 
-  assert_eq!(execute("1,0,0,0,99"), vec![2,0,0,0,99]);
-  assert_eq!(execute("2,3,0,3,99"), vec![2,3,0,6,99]);
-  assert_eq!(execute("2,4,4,5,99,0"), vec![2,4,4,5,99,9801]);
-  assert_eq!(execute("1,1,1,4,99,5,6,0,99"), vec![30,1,1,4,2,5,6,0,99]);
-}
\ No newline at end of file
+  assert_eq!(execute("1,0,0,0,99"), Some(vec![2,0,0,0,99]));
+  assert_eq!(execute("2,3,0,3,99"), Some(vec![2,3,0,6,99]));
+  assert_eq!(execute("2,4,4,5,99,0"), Some(vec![2,4,4,5,99,9801]));
+  assert_eq!(execute("1,1,1,4,99,5,6,0,99"), Some(vec![30,1,1,4,2,5,6,0,99]));
+
+  // Jumps back to itself forever.
+  assert_eq!(execute("1105,1,0"), None);
+}
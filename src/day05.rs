@@ -1,44 +1,47 @@
 use intcode::*;
 
 #[aoc_generator(day5)]
-pub fn day5_generator(input: &str) -> Vec<isize> {
+pub fn day5_generator(input: &str) -> Result<IntcodeProgram, ParseError> {
     intcode_parser(input)
 }
 
 fn execute_program(program: &IntcodeProgram, inputs: &Vec<isize>) -> Vec<isize> {
-    let mut memory = program.clone();
-    let mut machine = Machine::new(&mut memory);
+    let mut machine = Machine::new(program);
     for input in inputs {
         machine.push_input(*input);
     }
-    machine.run();
-    memory
+
+    machine.run_to_halt()
 }
 
 #[test]
 pub fn test_intcode() {
-  pub fn execute(input: &str) -> Vec<isize> {
-    execute_program(&intcode_parser(input), &vec![])
+  pub fn execute(input: &str, inputs: &Vec<isize>) -> Vec<isize> {
+    execute_program(&intcode_parser(input).unwrap(), inputs)
   }
 
-//   assert_eq!(execute("1,0,0,0,99"), vec![2,0,0,0,99]);
-//   assert_eq!(execute("2,3,0,3,99"), vec![2,3,0,6,99]);
-//   assert_eq!(execute("2,4,4,5,99,0"), vec![2,4,4,5,99,9801]);
-//   assert_eq!(execute("1,1,1,4,99,5,6,0,99"), vec![30,1,1,4,2,5,6,0,99]);
+  assert_eq!(execute("3,0,4,0,99", &vec![42]), vec![42]);
+  assert_eq!(execute("3,9,8,9,10,9,4,9,99,-1,8", &vec![8]), vec![1]);
+  assert_eq!(execute("3,3,1107,-1,8,3,4,3,99", &vec![9]), vec![0]);
+  assert_eq!(execute("3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9", &vec![0]), vec![0]);
 }
 
 #[aoc(day5, part1)]
-pub fn diagnostic(program: &Vec<isize>) -> String {
-    let output = execute_program(&mut program.clone(), &vec![1]);
+pub fn diagnostic(program: &Vec<isize>) -> isize {
+    let output = execute_program(program, &vec![1]);
+    let (code, tests) = output.split_last().expect("Diagnostic produced no output");
+
+    // Every test before the diagnostic code reports how far off it was.
+    assert!(tests.iter().all(|test| *test == 0), "Diagnostic tests failed: {:?}", tests);
 
-    format!("{:?}", output)
+    *code
 }
 
 
 #[aoc(day5, part2)]
-pub fn thermal(program: &Vec<isize>) -> String {
-    let output = execute_program(&mut program.clone(), &vec![5]);
+pub fn thermal(program: &Vec<isize>) -> isize {
+    let output = execute_program(program, &vec![5]);
 
-    format!("{:?}", output)
+    output[0]
 }
 
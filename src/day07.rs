@@ -1,45 +1,43 @@
 use intcode::*;
 
+// Instructions a single amplifier may execute before its phase setting is
+// given up on, so one runaway candidate cannot stall the search.
+const AMPLIFIER_BUDGET: u64 = 100_000;
+
 #[aoc_generator(day7)]
-pub fn day7_generator(input: &str) -> IntcodeProgram {
+pub fn day7_generator(input: &str) -> Result<IntcodeProgram, ParseError> {
     intcode_parser(input)
 }
 
 
-fn execute_amplifier(program: &IntcodeProgram, inputs: &Vec<isize>) -> isize {
-    let mut memory = program.clone();
-    let mut machine = Machine::new(&mut memory);
-    for input in inputs {
-        machine.push_input(*input);
-    }
+fn execute_amplifier_chain(program: &IntcodeProgram, sequence: &Vec<isize>) -> Option<isize> {
+    let mut amplifiers: Pipeline = Pipeline::series(program, sequence);
+    amplifiers.set_budget(Some(AMPLIFIER_BUDGET));
+    amplifiers.push_input(0, 0);
 
-    loop {
-        match machine.run() {
-            Action::RequiresInput => unimplemented!(),
-            Action::Output(output) => return output,
-            Action::Halt => panic!("Halted without producing output")
-        }
+    if !amplifiers.run() {
+        return None;
     }
-}
-
-fn execute_amplifier_chain(program: &IntcodeProgram, sequence: &Vec<isize>) -> isize {
-    let output_a = execute_amplifier(&program, &vec![sequence[0], 0]);
-    let output_b = execute_amplifier(&program, &vec![sequence[1], output_a]);
-    let output_c = execute_amplifier(&program, &vec![sequence[2], output_b]);
-    let output_d = execute_amplifier(&program, &vec![sequence[3], output_c]);
-    let output_e = execute_amplifier(&program, &vec![sequence[4], output_d]);
 
-    output_e
+    amplifiers.outputs(amplifiers.len() - 1).last().copied()
 }
 
 #[test]
 fn example_program_one() {
-    let program = intcode_parser("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0");
+    let program = intcode_parser("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0").unwrap();
     let sequence = vec![4,3,2,1,0];
 
     let output = execute_amplifier_chain(&program, &sequence);
 
-    assert_eq!(output, 43210);
+    assert_eq!(output, Some(43210));
+}
+
+#[test]
+fn runaway_amplifier() {
+    // Loops forever once it has read its phase setting.
+    let program = intcode_parser("3,9,1105,1,2,99").unwrap();
+
+    assert_eq!(execute_amplifier_chain(&program, &vec![0,1,2,3,4]), None);
 }
 
 #[aoc(day7, part1)]
@@ -61,8 +59,11 @@ pub fn find_amplifier_settings(program: &IntcodeProgram) -> isize {
                         if five == four || five == three || five == two || five == one { continue; }
 
                         let sequence = vec![one, two, three, four, five];
-                        let output = execute_amplifier_chain(&program, &sequence);
-                        
+                        let output = match execute_amplifier_chain(&program, &sequence) {
+                            Some(output) => output,
+                            None => continue
+                        };
+
                         if output > best_output {
                             best_output = output;
                         }
@@ -76,63 +77,17 @@ pub fn find_amplifier_settings(program: &IntcodeProgram) -> isize {
 }
 
 
-fn execute_amplifier_loop(program: &IntcodeProgram, sequence: &Vec<isize>) -> isize {
-    let mut amplifier_a = Machine::new(&program);
-    let mut amplifier_b = Machine::new(&program);
-    let mut amplifier_c = Machine::new(&program);
-    let mut amplifier_d = Machine::new(&program);
-    let mut amplifier_e = Machine::new(&program);
-
-    amplifier_a.push_input(sequence[0]);
-    amplifier_b.push_input(sequence[1]);
-    amplifier_c.push_input(sequence[2]);
-    amplifier_d.push_input(sequence[3]);
-    amplifier_e.push_input(sequence[4]);
-
-    amplifier_a.push_input(0);
-
-    let mut thruster_input = None;
-
-    'main: loop {
-        let output_a = match amplifier_a.run() {
-            Action::RequiresInput => unimplemented!(),
-            Action::Output(output) => output,
-            Action::Halt => break 'main
-        };
-
-        amplifier_b.push_input(output_a);
-        let output_b = match amplifier_b.run() {
-            Action::RequiresInput => unimplemented!(),
-            Action::Output(output) => output,
-            Action::Halt => break 'main
-        };
-
-        amplifier_c.push_input(output_b);
-        let output_c = match amplifier_c.run() {
-            Action::RequiresInput => unimplemented!(),
-            Action::Output(output) => output,
-            Action::Halt => break 'main
-        };
-
-        amplifier_d.push_input(output_c);
-        let output_d = match amplifier_d.run() {
-            Action::RequiresInput => unimplemented!(),
-            Action::Output(output) => output,
-            Action::Halt => break 'main
-        };
-
-        amplifier_e.push_input(output_d);
-        let output_e = match amplifier_e.run() {
-            Action::RequiresInput => unimplemented!(),
-            Action::Output(output) => output,
-            Action::Halt => break 'main
-        };
-
-        thruster_input = Some(output_e);
-        amplifier_a.push_input(output_e);
+fn execute_amplifier_loop(program: &IntcodeProgram, sequence: &Vec<isize>) -> Option<isize> {
+    let mut amplifiers: Pipeline = Pipeline::ring(program, sequence);
+    amplifiers.set_budget(Some(AMPLIFIER_BUDGET));
+    amplifiers.push_input(0, 0);
+
+    // Only a loop that ran to completion produced a real signal.
+    if !amplifiers.run() {
+        return None;
     }
 
-    thruster_input.unwrap()
+    amplifiers.outputs(amplifiers.len() - 1).last().copied()
 }
 
 #[aoc(day7, part2)]
@@ -154,8 +109,11 @@ pub fn find_amplifier_loop_settings(program: &IntcodeProgram) -> isize {
                         if five == four || five == three || five == two || five == one { continue; }
 
                         let sequence = vec![one, two, three, four, five];
-                        let output = execute_amplifier_loop(&program, &sequence);
-                        
+                        let output = match execute_amplifier_loop(&program, &sequence) {
+                            Some(output) => output,
+                            None => continue
+                        };
+
                         if output > best_output {
                             println!("New best sequence {:?} with output {}", sequence, output);
 
@@ -1,7 +1,7 @@
 use intcode::*;
 
 #[aoc_generator(day9)]
-pub fn day9_generator(input: &str) -> IntcodeProgram {
+pub fn day9_generator(input: &str) -> Result<IntcodeProgram, ParseError> {
     intcode_parser(input)
 }
 
@@ -10,17 +10,7 @@ pub fn boost(input: &IntcodeProgram) -> isize {
     let mut machine = Machine::new(input);
     machine.push_input(1);
 
-    let mut output = Vec::new();
-    
-    'main: loop { 
-        match machine.run() {
-            Action::RequiresInput => unimplemented!(),
-            Action::Output(value) => output.push(value),
-            Action::Halt => break 'main
-        }
-    }
-
-    output[0]
+    machine.run_until_output().expect("BOOST produced no keycode")
 }
 
 
@@ -29,15 +19,5 @@ pub fn sensor_boost(input: &IntcodeProgram) -> isize {
     let mut machine = Machine::new(input);
     machine.push_input(2);
 
-    let mut output = Vec::new();
-    
-    'main: loop { 
-        match machine.run() {
-            Action::RequiresInput => unimplemented!(),
-            Action::Output(value) => output.push(value),
-            Action::Halt => break 'main
-        }
-    }
-
-    output[0]
+    machine.run_until_output().expect("BOOST produced no coordinates")
 }
\ No newline at end of file
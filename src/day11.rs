@@ -65,60 +65,40 @@ enum Colour {
 }
 
 #[aoc_generator(day11)]
-pub fn parse_intcode(input: &str) -> IntcodeProgram {
+pub fn parse_intcode(input: &str) -> Result<IntcodeProgram, ParseError> {
     intcode_parser(input)
 }
 
 fn run_painting_robot(program: &IntcodeProgram, paint: &mut HashMap<Position, Colour>) {
-    let mut machine = Machine::new(program);
+    let mut robot = Machine::new(program).into_framed(Schema::named(&["colour", "turn"]));
 
     let mut position = Position(0,0);
     let mut direction = Direction::Up;
 
-    'main: loop {
-        let colour;
-        loop {
-            match machine.step() {
-                Some(Action::RequiresInput) => {
-                    machine.push_input(match paint.get(&position).unwrap_or(&Colour::Black) {
-                        Colour::Black => 0,
-                        Colour::White => 1
-                    });
-                },
-                Some(Action::Halt) => break 'main,
-                Some(Action::Output(value)) => {
-                    colour = match value {
-                        0 => Colour::Black,
-                        1 => Colour::White,
-                        _ => unimplemented!()
-                    };
-                    break;
-                },
-                None => {}
+    loop {
+        let record = match robot.read_record() {
+            Some(record) => record.expect("Robot halted partway through a move"),
+            None if robot.halted() => break,
+            None => {
+                robot.push_input(match paint.get(&position).unwrap_or(&Colour::Black) {
+                    Colour::Black => 0,
+                    Colour::White => 1
+                });
+                continue;
             }
-        }
-
-        let turn;
-        loop {
-            match machine.step() {
-                Some(Action::RequiresInput) => {
-                    machine.push_input(match paint.get(&position).unwrap_or(&Colour::Black) {
-                        Colour::Black => 0,
-                        Colour::White => 1
-                    });
-                },
-                Some(Action::Halt) => break 'main,
-                Some(Action::Output(value)) => {
-                    turn = match value {
-                        0 => Turn::Left,
-                        1 => Turn::Right,
-                        _ => unimplemented!()
-                    };
-                    break;
-                },
-                None => {}
-            }
-        }
+        };
+
+        let colour = match record["colour"] {
+            0 => Colour::Black,
+            1 => Colour::White,
+            _ => unimplemented!()
+        };
+
+        let turn = match record["turn"] {
+            0 => Turn::Left,
+            1 => Turn::Right,
+            _ => unimplemented!()
+        };
 
         paint.insert(position, colour);
         direction = direction.turn(&turn);
@@ -1,9 +1,7 @@
-use std::collections::VecDeque;
-
 use intcode::*;
 
 #[aoc_generator(day13)]
-pub fn parse_intcode(input: &str) -> IntcodeProgram {
+pub fn parse_intcode(input: &str) -> Result<IntcodeProgram, ParseError> {
     intcode_parser(input)
 }
 
@@ -24,35 +22,24 @@ struct DrawingInstruction {
 
 #[aoc(day13, part1)]
 pub fn count_blocks(program: &IntcodeProgram) -> usize {
-  let mut machine = Machine::new(program);
-  let mut drawing_instructions = Vec::new();
-  let mut output_buffer = VecDeque::new();
-
-  'main: loop { 
-    match machine.step() {
-      Some(Action::RequiresInput) => unimplemented!(),
-      Some(Action::Halt) => break 'main,
-      Some(Action::Output(value)) => {
-        output_buffer.push_back(value);
-        
-        if output_buffer.len() >= 3 {
-          drawing_instructions.push(DrawingInstruction {
-            x: output_buffer.pop_front().unwrap(),
-            y: output_buffer.pop_front().unwrap(),
-            tile_id: match output_buffer.pop_front().unwrap() {
-              0 => TileId::Empty,
-              1 => TileId::Wall,
-              2 => TileId::Block,
-              3 => TileId::HorizontalPaddle,
-              4 => TileId::Ball,
-              _ => unimplemented!()
-            }
-          });
-        }
-      },
-      None => {}
+  let screen = Machine::new(program).into_framed(Schema::named(&["x", "y", "tile_id"]));
+
+  let drawing_instructions = screen.map(|record| {
+    let values = record.expect("Screen output ended partway through a tile");
+
+    DrawingInstruction {
+      x: values["x"],
+      y: values["y"],
+      tile_id: match values["tile_id"] {
+        0 => TileId::Empty,
+        1 => TileId::Wall,
+        2 => TileId::Block,
+        3 => TileId::HorizontalPaddle,
+        4 => TileId::Ball,
+        _ => unimplemented!()
+      }
     }
-  }
+  });
 
   let mut block_tile_count = 0;
   for inst in drawing_instructions {
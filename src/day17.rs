@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use intcode::*;
 
 #[aoc_generator(day17)]
-pub fn parse_intcode(input: &str) -> IntcodeProgram {
+pub fn parse_intcode(input: &str) -> Result<IntcodeProgram, ParseError> {
     intcode_parser(input)
 }
 
@@ -45,45 +45,35 @@ fn is_intersection(map: &HashMap<Point, Cell>, point: &Point) -> bool {
 
 #[aoc(day17, part1)]
 pub fn find_alignment_parameters(program: &IntcodeProgram) -> isize {
-    let mut machine = Machine::new(program);
+    let mut camera = Machine::new(program).into_ascii();
 
     let mut map = HashMap::new();
-    let mut x = 0;
-    let mut y = 0;
-
-    let mut min_x = 0;
     let mut max_x = 0;
-    let mut min_y = 0;
     let mut max_y = 0;
 
-    'main: loop {
-        if x < min_x { min_x = x; }
-        if y < min_y { min_y = y; }
-        if x > max_x { max_x = x; }
-        if y > max_y { max_y = y; }
-
-        match machine.step() {
-            Some(Action::Halt) => { break 'main; },
-            Some(Action::Output(value)) => {
-                let cell = match value as u8 as char {
-                    '\n' => { y += 1; x = 0; continue 'main; },
-                    '.' => Cell::Open,
-                    '#'|'^'|'>'|'v'|'<' => Cell::Scaffold,
-                    c => panic!("Unknown cell character: '{}'", c)
-                };
-
-                map.insert(Point(x,y), cell);
-                x += 1;
-            },
-            Some(Action::RequiresInput) => { unimplemented!() }
-            None => {}
+    for (y, line) in camera.read_to_end().iter().enumerate() {
+        let line = match line {
+            AsciiOutput::Text(line) => line,
+            AsciiOutput::Value(value) => panic!("Unexpected output: {}", value)
+        };
+
+        for (x, c) in line.chars().enumerate() {
+            let cell = match c {
+                '.' => Cell::Open,
+                '#'|'^'|'>'|'v'|'<' => Cell::Scaffold,
+                c => panic!("Unknown cell character: '{}'", c)
+            };
+
+            map.insert(Point(x as isize, y as isize), cell);
+            max_x = max_x.max(x as isize);
+            max_y = max_y.max(y as isize);
         }
     }
 
     let mut sum = 0;
 
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
+    for y in 0..=max_y {
+        for x in 0..=max_x {
             let point = Point(x, y);
 
             if is_intersection(&map, &point) {
@@ -107,34 +97,24 @@ pub fn collect_dust(program: &IntcodeProgram) -> isize {
     let mut machine = Machine::new(program);
     machine.write(2, &Parameter::Position(0));
 
+    let mut robot = machine.into_ascii();
+
     // solved by hand
     let input = [
-        "A,C,A,B,A,C,B,C,B,C",
-        "R,8,L,10,L,12,R,4",
-        "R,8,L,10,R,8",
-        "R,8,L,12,R,4,R,4",
-        "n",
-        ""
-    ].join("\n");
-
-    for c in input.chars() {
-        machine.push_input(c as isize);
+        ("Main:", "A,C,A,B,A,C,B,C,B,C"),
+        ("Function A:", "R,8,L,10,L,12,R,4"),
+        ("Function B:", "R,8,L,10,R,8"),
+        ("Function C:", "R,8,L,12,R,4,R,4"),
+        ("Continuous video feed?", "n"),
+    ];
+
+    for (prompt, line) in input.iter() {
+        robot.read_until_prompt(prompt);
+        robot.send_line(line);
     }
 
-
-    let mut dust = None;
-    'main: loop {
-        match machine.step() {
-            Some(Action::Halt) => {
-                break 'main;
-            },
-            Some(Action::Output(value)) => { 
-                dust = Some(value);
-            },
-            Some(Action::RequiresInput) => unimplemented!(),
-            None => {}
-        }
+    match robot.read_to_end().pop() {
+        Some(AsciiOutput::Value(dust)) => dust,
+        _ => panic!("Did not output amount of dust collected")
     }
-
-    dust.expect("Did not output amount of dust collected")
 }
\ No newline at end of file
@@ -3,34 +3,33 @@ use intcode::*;
 const BOX_SIZE: usize = 100;
 
 #[aoc_generator(day19)]
-pub fn parse_intcode(input: &str) -> IntcodeProgram {
+pub fn parse_intcode(input: &str) -> Result<IntcodeProgram, ParseError> {
     intcode_parser(input)
 }
 
-fn probe_location(program: &IntcodeProgram, x: usize, y: usize) -> bool {
+// Each probe runs a fresh copy of this machine, which has its instructions
+// decoded already.
+fn drone(program: &IntcodeProgram) -> Machine {
     let mut machine = Machine::new(program);
-    machine.push_input(x as isize);
-    machine.push_input(y as isize);
+    machine.warm_instruction_cache();
+    machine
+}
 
-    'main: loop { 
-        match machine.step() {
-            Some(Action::RequiresInput) => unimplemented!(),
-            Some(Action::Halt) => unimplemented!(),
-            Some(Action::Output(value)) => {
-                return value == 1;
-            },
-            None => {}
-        }
-    }
+fn probe_location(drone: &Machine, x: usize, y: usize) -> bool {
+    let mut machine = drone.clone();
+    let output = machine.outputs_with(IterSource::new(vec![x as isize, y as isize])).next();
+
+    output == Some(1)
 }
 
 #[aoc(day19, part1)]
 pub fn count_points(program: &IntcodeProgram) -> usize {
+    let drone = drone(program);
     let mut count: usize = 0;
 
     for y in 0..50 {
         for x in 0..50 {
-            if probe_location(program, x, y) {
+            if probe_location(&drone, x, y) {
                 print!("#");
                 count += 1;
             } else {
@@ -45,11 +44,12 @@ pub fn count_points(program: &IntcodeProgram) -> usize {
 
 #[aoc(day19, part2)]
 pub fn find_ship(program: &IntcodeProgram) -> usize {
-    
+    let drone = drone(program);
+
     for y in 1000usize..10000usize {
         let mut on_column = None;
         for x in 500usize..10000usize {
-            if probe_location(program, x, y) {
+            if probe_location(&drone, x, y) {
                 on_column = Some(x);
                 break;
             }
@@ -59,7 +59,7 @@ pub fn find_ship(program: &IntcodeProgram) -> usize {
         }
         let on_column = on_column.unwrap();
 
-        if probe_location(program, on_column + (BOX_SIZE-1), y - (BOX_SIZE-1)) {
+        if probe_location(&drone, on_column + (BOX_SIZE-1), y - (BOX_SIZE-1)) {
             return (on_column * 10000) + (y - (BOX_SIZE-1));
         }
     }
@@ -1,14 +1,37 @@
 use intcode::*;
 
 #[aoc_generator(day21)]
-pub fn generator(input: &str) -> IntcodeProgram {
+pub fn generator(input: &str) -> Result<IntcodeProgram, ParseError> {
     intcode_parser(input)
 }
 
+// Feeds the script to the springdroid and adds up the damage it reports.
+fn run_springscript(program: &IntcodeProgram, script: &[&str]) -> isize {
+    let mut springdroid = Machine::new(program).into_ascii();
+
+    springdroid.read_until_prompt("Input instructions:");
+
+    for line in script {
+        springdroid.send_line(line);
+    }
+
+    let mut damage_taken = 0;
+
+    for output in springdroid.read_to_end() {
+        match output {
+            AsciiOutput::Text(line) => println!("{}", line),
+            AsciiOutput::Value(value) => {
+                println!("Took damage: {}", value);
+                damage_taken += value;
+            }
+        }
+    }
+
+    damage_taken
+}
+
 #[aoc(day21, part1)]
 pub fn part1(program: &IntcodeProgram) -> isize {
-    let mut machine = Machine::new(program);
-
     // @ABCD
     // jump if (!A|!B|!C) && D
     let input = [
@@ -18,40 +41,15 @@ pub fn part1(program: &IntcodeProgram) -> isize {
         "NOT C T",
         "OR T J",
         "AND D J",
-        "WALK",
-        ""
-    ].join("\n");
-
-    for c in input.chars() {
-        machine.push_input(c as isize);
-    }
+        "WALK"
+    ];
 
-    let mut damage_taken = 0;
-
-    'main: loop {
-        match machine.step() {
-            Some(Action::RequiresInput) => unimplemented!(),
-            Some(Action::Halt) => break 'main,
-            Some(Action::Output(value)) => {
-                if value <= 255 {
-                    print!("{}", value as u8 as char);
-                } else {
-                    println!("Took damage: {}", value);
-                    damage_taken += value;
-                }
-            },
-            None => {}
-        }
-    }
-
-    damage_taken
+    run_springscript(program, &input)
 }
 
 
 #[aoc(day21, part2)]
 pub fn part2(program: &IntcodeProgram) -> isize {
-    let mut machine = Machine::new(program);
-
     let input = [
         // (!A|!B|!C)&D &(E|H)
 
@@ -68,31 +66,8 @@ pub fn part2(program: &IntcodeProgram) -> isize {
         "OR H T",
         "AND T J",
 
-        "RUN",
-        ""
-    ].join("\n");
+        "RUN"
+    ];
 
-    for c in input.chars() {
-        machine.push_input(c as isize);
-    }
-
-    let mut damage_taken = 0;
-
-    'main: loop {
-        match machine.step() {
-            Some(Action::RequiresInput) => unimplemented!(),
-            Some(Action::Halt) => break 'main,
-            Some(Action::Output(value)) => {
-                if value <= 255 {
-                    print!("{}", value as u8 as char);
-                } else {
-                    println!("Took damage: {}", value);
-                    damage_taken += value;
-                }
-            },
-            None => {}
-        }
-    }
-
-    damage_taken
+    run_springscript(program, &input)
 }
\ No newline at end of file